@@ -1,28 +1,98 @@
 use std::fs;
 use std::io::{self, Read, Write};
+use std::path::Path;
 
-use aasvg::RenderOptions;
-use facet::Facet;
+use aasvg::{is_attribute_name, ColorMode, RenderOptions, SourceOverlay};
+use facet::{Facet, Type, UserType};
 use facet_args as args;
 
 /// Convert ASCII art diagrams to SVG
+///
+/// Other `--<attr>=<value>` options set attributes of the root `<svg>`,
+/// e.g. `--class=figure` or `--font-family=monospace`.
 #[derive(Facet, Debug)]
 struct Args {
     /// Input file (reads from stdin if not provided)
     #[facet(default, args::positional)]
     input: Option<String>,
 
-    /// Output file (writes to stdout if not provided)
+    /// Output file (stdout if not provided); .png, .pdf, .json, .dot/.gv or .mmd pick the format
     #[facet(default, args::named, args::short = 'o')]
     output: Option<String>,
 
     /// Add a backdrop rectangle for dark mode compatibility
     #[facet(args::named)]
     backdrop: bool,
+
+    /// Draw only lines and decorations, without the text
+    #[facet(args::named)]
+    disable_text: bool,
+
+    /// Consecutive spaces that end a text run (default 2)
+    #[facet(default, args::named)]
+    spaces: Option<u32>,
+
+    /// Stretch text to fill its character cells exactly
+    #[facet(args::named)]
+    stretch: bool,
+
+    /// Colors to use: adaptive (default), light or dark
+    #[facet(default, args::named)]
+    color_mode: Option<String>,
+
+    /// Overlay the character-cell grid and mark where each line ends
+    #[facet(args::named)]
+    grid: bool,
+
+    /// Tint the cells consumed by lines and decorations
+    #[facet(args::named)]
+    used_cells: bool,
+
+    /// Draw the source characters faintly over the diagram
+    #[facet(args::named)]
+    source: bool,
+
+    /// Leave out width and height so the SVG scales to its container
+    #[facet(args::named)]
+    fill: bool,
+
+    /// Viewport width in characters, padding or cropping the diagram
+    #[facet(default, args::named)]
+    width: Option<usize>,
+
+    /// Viewport height in characters, padding or cropping the diagram
+    #[facet(default, args::named)]
+    height: Option<usize>,
+
+    /// Pixels added around the diagram (negative values trim, down to one cell), e.g. --margin=-4
+    #[facet(default, args::named)]
+    margin: Option<f64>,
+
+    /// Width of a character cell in pixels (default 8), e.g. 9.6 for a 0.6em font at 16px
+    #[facet(default, args::named)]
+    cell_width: Option<f64>,
+
+    /// Cell height as a multiple of the cell width (default 2)
+    #[facet(default, args::named)]
+    aspect: Option<f64>,
+
+    /// Font size of the text in pixels (default 13)
+    #[facet(default, args::named)]
+    font_size: Option<f64>,
+
+    /// Print the version and exit
+    #[facet(args::named)]
+    version: bool,
+
+    /// Scale factor for PNG output
+    #[facet(default, args::named)]
+    scale: Option<f32>,
 }
 
 fn main() {
-    let args: Args = match args::from_std_args() {
+    let (cli_args, attributes) = split_attributes(std::env::args().skip(1));
+    let cli_args: Vec<&str> = cli_args.iter().map(String::as_str).collect();
+    let args: Args = match args::from_slice(&cli_args) {
         Ok(args) => args,
         Err(e) => {
             eprintln!("{e}");
@@ -30,6 +100,40 @@ fn main() {
         }
     };
 
+    if args.version {
+        println!("aasvg {}", env!("CARGO_PKG_VERSION"));
+        return;
+    }
+    if let Some((name, _)) = attributes.iter().find(|(name, _)| !is_attribute_name(name)) {
+        eprintln!("Invalid attribute name {:?}", name);
+        std::process::exit(1);
+    }
+    let sizes = [
+        ("scale", args.scale.map(f64::from)),
+        ("cell-width", args.cell_width),
+        ("aspect", args.aspect),
+        ("font-size", args.font_size),
+    ];
+    for (name, value) in sizes {
+        if let Some(value) = value.filter(|v| !(v.is_finite() && *v > 0.0)) {
+            eprintln!("Invalid --{} {}: must be a positive number", name, value);
+            std::process::exit(1);
+        }
+    }
+
+    let color_mode = match args.color_mode.as_deref() {
+        None | Some("adaptive") => ColorMode::Adaptive,
+        Some("light") => ColorMode::Light,
+        Some("dark") => ColorMode::Dark,
+        Some(other) => {
+            eprintln!(
+                "Invalid --color-mode {}: must be adaptive, light or dark",
+                other
+            );
+            std::process::exit(1);
+        }
+    };
+
     let input = match &args.input {
         Some(path) => fs::read_to_string(path).unwrap_or_else(|e| {
             eprintln!("Failed to read {}: {}", path, e);
@@ -45,21 +149,113 @@ fn main() {
         }
     };
 
-    let options = RenderOptions::new().with_backdrop(args.backdrop);
-    let svg = aasvg::render_with_options(&input, &options);
+    let mut options = RenderOptions::new()
+        .with_backdrop(args.backdrop)
+        .with_disable_text(args.disable_text)
+        .with_stretch(args.stretch)
+        .with_color_mode(color_mode)
+        .with_show_grid(args.grid)
+        .with_show_used(args.used_cells)
+        .with_fill_container(args.fill)
+        .with_size(args.width, args.height)
+        .with_margin(args.margin.unwrap_or(0.0));
+    for (name, value) in attributes {
+        options = options.with_attribute(name, value);
+    }
+    if args.source {
+        options = options.with_source_overlay(SourceOverlay::Over);
+    }
+    if let Some(spaces) = args.spaces {
+        options = options.with_spaces(spaces);
+    }
+    if let Some(cell_width) = args.cell_width {
+        options = options.with_cell_width(cell_width);
+    }
+    if let Some(aspect) = args.aspect {
+        options = options.with_aspect(aspect);
+    }
+    if let Some(font_size) = args.font_size {
+        options = options.with_font_size(font_size);
+    }
+    let extension = args
+        .output
+        .as_deref()
+        .and_then(|path| Path::new(path).extension())
+        .map(|ext| ext.to_ascii_lowercase());
+    let output = match extension.as_ref().and_then(|ext| ext.to_str()) {
+        #[cfg(feature = "png")]
+        Some("png") => aasvg::render_png(&input, &options, args.scale.unwrap_or(1.0))
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to render PNG: {}", e);
+                std::process::exit(1);
+            }),
+        #[cfg(feature = "pdf")]
+        Some("pdf") => aasvg::render_pdf(&input, &options),
+        #[cfg(not(feature = "png"))]
+        Some("png") => unsupported("png"),
+        #[cfg(not(feature = "pdf"))]
+        Some("pdf") => unsupported("pdf"),
+        Some("json") => graph(&input, &options).to_json().into_bytes(),
+        Some("dot" | "gv") => graph(&input, &options).to_dot().into_bytes(),
+        Some("mmd") => graph(&input, &options).to_mermaid().into_bytes(),
+        _ => aasvg::render_with_options(&input, &options).into_bytes(),
+    };
 
     match &args.output {
         Some(path) => {
-            fs::write(path, &svg).unwrap_or_else(|e| {
+            fs::write(path, &output).unwrap_or_else(|e| {
                 eprintln!("Failed to write {}: {}", path, e);
                 std::process::exit(1);
             });
         }
         None => {
-            io::stdout().write_all(svg.as_bytes()).unwrap_or_else(|e| {
+            io::stdout().write_all(&output).unwrap_or_else(|e| {
                 eprintln!("Failed to write stdout: {}", e);
                 std::process::exit(1);
             });
         }
     }
 }
+
+/// Take `--<attr>=<value>` arguments that are not options of [`Args`] out
+/// of `args`, as attributes for the root `<svg>` element
+fn split_attributes(args: impl Iterator<Item = String>) -> (Vec<String>, Vec<(String, String)>) {
+    let options: Vec<String> = match Args::SHAPE.ty {
+        Type::User(UserType::Struct(fields)) => fields
+            .fields
+            .iter()
+            .map(|field| field.name.replace('_', "-"))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let mut rest = Vec::new();
+    let mut attributes = Vec::new();
+    let mut positional_only = false;
+    for arg in args {
+        positional_only |= arg == "--";
+        let attribute = arg.strip_prefix("--").and_then(|arg| arg.split_once('='));
+        match attribute {
+            Some((name, value)) if !positional_only && !options.iter().any(|o| o == name) => {
+                attributes.push((name.to_string(), value.to_string()));
+            }
+            _ => rest.push(arg),
+        }
+    }
+    (rest, attributes)
+}
+
+/// Exit for an output format the binary was built without
+#[cfg(not(all(feature = "png", feature = "pdf")))]
+fn unsupported(format: &str) -> ! {
+    eprintln!(
+        "Cannot write {}: built without {} support",
+        format.to_uppercase(),
+        format
+    );
+    std::process::exit(1);
+}
+
+/// Boxes and connections, with text split into runs as for rendering
+fn graph(input: &str, options: &RenderOptions) -> aasvg::Graph {
+    aasvg::parse_with_options(input, options).to_graph()
+}
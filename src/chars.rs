@@ -27,6 +27,205 @@ pub const GRAY_CHARS: &str = "▁▂▃█";
 /// Triangle decoration characters
 pub const TRI_CHARS: &str = "◢◣◤◥";
 
+/// Box-drawing dash characters for horizontal dashed lines
+pub const DASHED_H_LINE_CHARS: &str = "┄┅┈┉╌╍";
+
+/// Box-drawing dash characters for vertical dashed lines (`:` also works)
+pub const DASHED_V_LINE_CHARS: &str = "┆┇┊┋╎╏";
+
+/// Heavy box-drawing dash characters, drawn with the thick stroke
+pub const HEAVY_DASHED_LINE_CHARS: &str = "┅┉╍┇┋╏";
+
+/// Rounded box-drawing corners, rendered as quarter arcs
+pub const ROUNDED_CORNER_CHARS: &str = "╭╮╯╰";
+
+// ============================================================================
+// Box-drawing characters
+// ============================================================================
+
+/// Weight of one arm of a box-drawing character
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arm {
+    None,
+    Light,
+    Heavy,
+    Double,
+}
+
+impl Arm {
+    /// Returns true for arms drawn as a single stroke (light or heavy)
+    #[inline]
+    pub fn is_single(self) -> bool {
+        self == Arm::Light || self == Arm::Heavy
+    }
+
+    /// Returns true for double arms
+    #[inline]
+    pub fn is_double(self) -> bool {
+        self == Arm::Double
+    }
+}
+
+/// The four arms of a box-drawing character
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoxArms {
+    pub up: Arm,
+    pub right: Arm,
+    pub down: Arm,
+    pub left: Arm,
+}
+
+impl BoxArms {
+    /// Number of arms present
+    pub fn count(&self) -> usize {
+        [self.up, self.right, self.down, self.left]
+            .iter()
+            .filter(|&&arm| arm != Arm::None)
+            .count()
+    }
+
+    /// Returns true if the arms form a straight horizontal or vertical line
+    pub fn is_straight(&self) -> bool {
+        let horizontal = self.left != Arm::None || self.right != Arm::None;
+        let vertical = self.up != Arm::None || self.down != Arm::None;
+        horizontal != vertical
+    }
+
+    /// Returns true for a corner with two double arms, such as `╔`
+    pub fn is_double_corner(&self) -> bool {
+        let arms = [self.up, self.right, self.down, self.left];
+        self.count() == 2
+            && !self.is_straight()
+            && arms.iter().all(|&arm| arm == Arm::None || arm.is_double())
+    }
+
+    /// Returns true if the left or right arm satisfies `f`
+    #[inline]
+    pub fn any_h(&self, f: impl Fn(Arm) -> bool) -> bool {
+        f(self.left) || f(self.right)
+    }
+
+    /// Returns true if the up or down arm satisfies `f`
+    #[inline]
+    pub fn any_v(&self, f: impl Fn(Arm) -> bool) -> bool {
+        f(self.up) || f(self.down)
+    }
+}
+
+/// Arms for U+2500..U+257F, in up/right/down/left order:
+/// `l` light, `h` heavy, `d` double, `.` none.
+/// Dashes and diagonals have no arms; they are handled separately.
+#[rustfmt::skip]
+const BOX_ARMS: [&str; 128] = [
+    ".l.l", ".h.h", "l.l.", "h.h.", "....", "....", "....", "....", // ─━│┃┄┅┆┇
+    "....", "....", "....", "....", ".ll.", ".hl.", ".lh.", ".hh.", // ┈┉┊┋┌┍┎┏
+    "..ll", "..lh", "..hl", "..hh", "ll..", "lh..", "hl..", "hh..", // ┐┑┒┓└┕┖┗
+    "l..l", "l..h", "h..l", "h..h", "lll.", "lhl.", "hll.", "llh.", // ┘┙┚┛├┝┞┟
+    "hlh.", "hhl.", "lhh.", "hhh.", "l.ll", "l.lh", "h.ll", "l.hl", // ┠┡┢┣┤┥┦┧
+    "h.hl", "h.lh", "l.hh", "h.hh", ".lll", ".llh", ".hll", ".hlh", // ┨┩┪┫┬┭┮┯
+    ".lhl", ".lhh", ".hhl", ".hhh", "ll.l", "ll.h", "lh.l", "lh.h", // ┰┱┲┳┴┵┶┷
+    "hl.l", "hl.h", "hh.l", "hh.h", "llll", "lllh", "lhll", "lhlh", // ┸┹┺┻┼┽┾┿
+    "hlll", "llhl", "hlhl", "hllh", "hhll", "llhh", "lhhl", "hhlh", // ╀╁╂╃╄╅╆╇
+    "lhhh", "hlhh", "hhhl", "hhhh", "....", "....", "....", "....", // ╈╉╊╋╌╍╎╏
+    ".d.d", "d.d.", ".dl.", ".ld.", ".dd.", "..ld", "..dl", "..dd", // ═║╒╓╔╕╖╗
+    "ld..", "dl..", "dd..", "l..d", "d..l", "d..d", "ldl.", "dld.", // ╘╙╚╛╜╝╞╟
+    "ddd.", "l.ld", "d.dl", "d.dd", ".dld", ".ldl", ".ddd", "ld.d", // ╠╡╢╣╤╥╦╧
+    "dl.l", "dd.d", "ldld", "dldl", "dddd", ".ll.", "..ll", "l..l", // ╨╩╪╫╬╭╮╯
+    "ll..", "....", "....", "....", "...l", "l...", ".l..", "..l.", // ╰╱╲╳╴╵╶╷
+    "...h", "h...", ".h..", "..h.", ".h.l", "l.h.", ".l.h", "h.l.", // ╸╹╺╻╼╽╾╿
+];
+
+/// Arms of a box-drawing character, or None for anything else
+pub fn box_arms(c: char) -> Option<BoxArms> {
+    let index = (c as u32).checked_sub(0x2500)? as usize;
+    let spec = BOX_ARMS.get(index)?.as_bytes();
+    let arm = |b: u8| match b {
+        b'l' => Arm::Light,
+        b'h' => Arm::Heavy,
+        b'd' => Arm::Double,
+        _ => Arm::None,
+    };
+    let arms = BoxArms {
+        up: arm(spec[0]),
+        right: arm(spec[1]),
+        down: arm(spec[2]),
+        left: arm(spec[3]),
+    };
+    if arms.count() == 0 {
+        None
+    } else {
+        Some(arms)
+    }
+}
+
+/// Returns true if the character is a box-drawing corner, tee or cross
+#[inline]
+pub fn is_box_vertex(c: char) -> bool {
+    box_arms(c).is_some_and(|arms| arms.count() >= 2 && !arms.is_straight())
+}
+
+/// Returns true if the character is a rounded box-drawing corner (╭╮╯╰)
+#[inline]
+pub fn is_rounded_corner(c: char) -> bool {
+    ROUNDED_CORNER_CHARS.contains(c)
+}
+
+/// Returns false if two horizontally adjacent characters are box-drawing
+/// characters whose arms do not meet (e.g. `┐┌`)
+#[inline]
+pub fn box_joins_h(left: char, right: char) -> bool {
+    box_arms(left).map_or(true, |arms| arms.right != Arm::None)
+        && box_arms(right).map_or(true, |arms| arms.left != Arm::None)
+}
+
+/// Returns false if two vertically adjacent characters are box-drawing
+/// characters whose arms do not meet (e.g. `┴` over `┬`)
+#[inline]
+pub fn box_joins_v(top: char, bottom: char) -> bool {
+    box_arms(top).map_or(true, |arms| arms.down != Arm::None)
+        && box_arms(bottom).map_or(true, |arms| arms.up != Arm::None)
+}
+
+/// Left and right arms of a line character; `-` counts as light
+pub fn h_arms(c: char) -> (Arm, Arm) {
+    match box_arms(c) {
+        Some(arms) => (arms.left, arms.right),
+        None if c == '-' => (Arm::Light, Arm::Light),
+        None => (Arm::None, Arm::None),
+    }
+}
+
+/// Up and down arms of a line character; `|` counts as light
+pub fn v_arms(c: char) -> (Arm, Arm) {
+    match box_arms(c) {
+        Some(arms) => (arms.up, arms.down),
+        None if c == '|' => (Arm::Light, Arm::Light),
+        None => (Arm::None, Arm::None),
+    }
+}
+
+/// Returns true if two arms that meet have different weights
+#[inline]
+fn weight_differs(a: Arm, b: Arm) -> bool {
+    a != Arm::None && b != Arm::None && a != b
+}
+
+/// Returns true if a horizontal line must be split between `left` and
+/// `right` because its weight changes there (e.g. `─━` or `╼━`)
+pub fn weight_changes_h(left: char, right: char) -> bool {
+    let (left_in, left_out) = h_arms(left);
+    let (right_in, _) = h_arms(right);
+    weight_differs(left_in, left_out) || weight_differs(left_out, right_in)
+}
+
+/// Returns true if a vertical line must be split between `top` and
+/// `bottom` because its weight changes there
+pub fn weight_changes_v(top: char, bottom: char) -> bool {
+    let (top_in, top_out) = v_arms(top);
+    let (bottom_in, _) = v_arms(bottom);
+    weight_differs(top_in, top_out) || weight_differs(top_out, bottom_in)
+}
+
 // ============================================================================
 // Vertex classification
 // ============================================================================
@@ -34,7 +233,7 @@ pub const TRI_CHARS: &str = "◢◣◤◥";
 /// Returns true if the character is part of the line network (a vertex/junction)
 #[inline]
 pub fn is_vertex(c: char) -> bool {
-    VERTEX_CHARS.contains(c)
+    VERTEX_CHARS.contains(c) || is_box_vertex(c)
 }
 
 /// Returns true if the character is an undirected vertex (+)
@@ -89,7 +288,11 @@ pub fn is_vertex_or_right_decoration(c: char) -> bool {
 /// Returns true if the character is a solid horizontal line segment
 #[inline]
 pub fn is_solid_h_line(c: char) -> bool {
-    c == '-' || c == '─' || c == '+' || c == '(' || c == ')'
+    c == '-'
+        || c == '+'
+        || c == '('
+        || c == ')'
+        || box_arms(c).is_some_and(|arms| arms.any_h(Arm::is_single))
 }
 
 /// Returns true if the character is a squiggle/wave horizontal line segment
@@ -101,25 +304,56 @@ pub fn is_squiggle_h_line(c: char) -> bool {
 /// Returns true if the character is a double horizontal line segment
 #[inline]
 pub fn is_double_h_line(c: char) -> bool {
-    c == '=' || c == '═' || c == '+' || c == '(' || c == ')'
+    c == '='
+        || c == '+'
+        || c == '('
+        || c == ')'
+        || box_arms(c).is_some_and(|arms| arms.any_h(Arm::is_double))
+}
+
+/// Returns true if the character is a dashed horizontal line segment
+/// Box-drawing vertices with a horizontal arm join dashed lines like `+` does
+#[inline]
+pub fn is_dashed_h_line(c: char) -> bool {
+    DASHED_H_LINE_CHARS.contains(c)
+        || c == '+'
+        || c == '('
+        || c == ')'
+        || (is_box_vertex(c) && box_arms(c).is_some_and(|arms| arms.any_h(Arm::is_single)))
+}
+
+/// Returns true if the character is a heavy box-drawing dash (e.g. `┅`, `┇`)
+#[inline]
+pub fn is_heavy_dashed_line(c: char) -> bool {
+    HEAVY_DASHED_LINE_CHARS.contains(c)
 }
 
 /// Returns true if the character is any horizontal line type
 #[inline]
 pub fn is_any_h_line(c: char) -> bool {
-    is_solid_h_line(c) || is_squiggle_h_line(c) || is_double_h_line(c)
+    is_solid_h_line(c) || is_squiggle_h_line(c) || is_double_h_line(c) || is_dashed_h_line(c)
 }
 
 /// Returns true if the character is a solid vertical line segment
 #[inline]
 pub fn is_solid_v_line(c: char) -> bool {
-    c == '|' || c == '│' || c == '+'
+    c == '|' || c == '+' || box_arms(c).is_some_and(|arms| arms.any_v(Arm::is_single))
 }
 
 /// Returns true if the character is a double vertical line segment
 #[inline]
 pub fn is_double_v_line(c: char) -> bool {
-    c == '║' || c == '+'
+    c == '+' || box_arms(c).is_some_and(|arms| arms.any_v(Arm::is_double))
+}
+
+/// Returns true if the character is a dashed vertical line segment
+/// Box-drawing vertices with a vertical arm join dashed lines like `+` does
+#[inline]
+pub fn is_dashed_v_line(c: char) -> bool {
+    c == ':'
+        || DASHED_V_LINE_CHARS.contains(c)
+        || c == '+'
+        || (is_box_vertex(c) && box_arms(c).is_some_and(|arms| arms.any_v(Arm::is_single)))
 }
 
 /// Returns true if the character is a forward slash diagonal (/)
@@ -184,13 +418,13 @@ pub fn is_ascii_letter(c: char) -> bool {
     c.is_ascii_alphabetic()
 }
 
-/// Gray level for fill characters (0-255)
+/// Gray level for fill characters, from 1 (least ink) to 4 (solid)
 pub fn gray_level(c: char) -> u8 {
     match c {
-        '▁' => 64,
-        '▂' => 128,
-        '▃' => 191,
-        '█' => 255,
+        '▁' => 1,
+        '▂' => 2,
+        '▃' => 3,
+        '█' => 4,
         _ => 0,
     }
 }
@@ -237,6 +471,100 @@ mod tests {
         assert!(is_solid_b_line('\\'));
     }
 
+    #[test]
+    fn test_dashed_line_detection() {
+        assert!(is_dashed_h_line('┄'));
+        assert!(is_dashed_h_line('╌'));
+        assert!(is_dashed_h_line('+'));
+        assert!(!is_dashed_h_line('-'));
+        assert!(is_any_h_line('┈'));
+
+        assert!(is_dashed_v_line(':'));
+        assert!(is_dashed_v_line('┆'));
+        assert!(is_dashed_v_line('╎'));
+        assert!(!is_dashed_v_line('|'));
+
+        assert!(is_heavy_dashed_line('┅'));
+        assert!(is_heavy_dashed_line('╏'));
+        assert!(!is_heavy_dashed_line('┄'));
+        assert!(!is_heavy_dashed_line(':'));
+    }
+
+    #[test]
+    fn test_box_arms() {
+        let arms = box_arms('┌').unwrap();
+        assert_eq!(arms.right, Arm::Light);
+        assert_eq!(arms.down, Arm::Light);
+        assert_eq!(arms.up, Arm::None);
+        assert_eq!(arms.left, Arm::None);
+
+        let arms = box_arms('╞').unwrap();
+        assert_eq!(arms.up, Arm::Light);
+        assert_eq!(arms.right, Arm::Double);
+        assert_eq!(arms.down, Arm::Light);
+
+        assert_eq!(box_arms('┿').unwrap().left, Arm::Heavy);
+        assert_eq!(box_arms('╭'), box_arms('┌'));
+        assert!(box_arms('╔').unwrap().is_double_corner());
+        assert!(!box_arms('╒').unwrap().is_double_corner());
+        assert!(!box_arms('╦').unwrap().is_double_corner());
+        assert_eq!(box_arms('┄'), None);
+        assert_eq!(box_arms('╱'), None);
+        assert_eq!(box_arms('-'), None);
+    }
+
+    #[test]
+    fn test_box_drawing_lines() {
+        for c in "┌┐└┘├┤┬┴┼╭╮╯╰".chars() {
+            assert!(is_vertex(c), "{c}");
+        }
+        assert!(!is_vertex('─'));
+        assert!(!is_vertex('╷'));
+
+        assert!(is_solid_h_line('┬'));
+        assert!(is_solid_h_line('━'));
+        assert!(!is_solid_h_line('│'));
+        assert!(is_solid_v_line('├'));
+        assert!(is_solid_v_line('┃'));
+        assert!(!is_solid_v_line('─'));
+
+        // Mixed junctions split between solid and double
+        assert!(is_double_h_line('╪'));
+        assert!(is_solid_v_line('╪'));
+        assert!(!is_solid_h_line('╪'));
+        assert!(is_double_v_line('╟'));
+        assert!(is_solid_h_line('╟'));
+
+        // Box vertices join dashed lines, straight solid lines do not
+        assert!(is_dashed_h_line('┌'));
+        assert!(!is_dashed_h_line('─'));
+        assert!(is_dashed_v_line('└'));
+        assert!(!is_dashed_v_line('│'));
+    }
+
+    #[test]
+    fn test_box_joins() {
+        assert!(box_joins_h('─', '┐'));
+        assert!(box_joins_h('-', '┐'));
+        assert!(!box_joins_h('┐', '┌'));
+        assert!(box_joins_v('┬', '│'));
+        assert!(!box_joins_v('┴', '┬'));
+    }
+
+    #[test]
+    fn test_weight_changes() {
+        assert_eq!(h_arms('━'), (Arm::Heavy, Arm::Heavy));
+        assert_eq!(h_arms('-'), (Arm::Light, Arm::Light));
+        assert_eq!(v_arms('┍'), (Arm::None, Arm::Light));
+
+        assert!(!weight_changes_h('━', '┓'));
+        assert!(!weight_changes_h('+', '━'));
+        assert!(weight_changes_h('─', '━'));
+        assert!(weight_changes_h('╼', '━'));
+        assert!(!weight_changes_v('┃', '┻'));
+        assert!(weight_changes_v('│', '┃'));
+    }
+
     #[test]
     fn test_arrow_detection() {
         assert!(is_arrow_head('>'));
@@ -258,10 +586,10 @@ mod tests {
 
     #[test]
     fn test_gray_levels() {
-        assert_eq!(gray_level('▁'), 64);
-        assert_eq!(gray_level('▂'), 128);
-        assert_eq!(gray_level('▃'), 191);
-        assert_eq!(gray_level('█'), 255);
+        assert_eq!(gray_level('▁'), 1);
+        assert_eq!(gray_level('▂'), 2);
+        assert_eq!(gray_level('▃'), 3);
+        assert_eq!(gray_level('█'), 4);
         assert_eq!(gray_level('x'), 0);
     }
 }
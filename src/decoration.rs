@@ -3,8 +3,12 @@
 // Many methods are provided for library consumers but not used internally
 #![allow(dead_code)]
 
+use std::fmt::{self, Write};
+
 use crate::chars::{gray_level, tri_angle};
-use crate::path::{diagonal_angle, Vec2, ASPECT, SCALE};
+use crate::path::{diagonal_angle, Coord, Metrics, Vec2, ASPECT, SCALE};
+use crate::shape::{circle, polygon, rotate, Paint, Segment, Shape};
+use crate::theme::Colors;
 
 /// Type of decoration
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -25,6 +29,7 @@ pub enum DecorationType {
     /// Parameter is the jump character: '(' or ')'
     Jump(char),
     /// Gray fill rectangle
+    /// Parameter is the gray level, from 1 (▁) to 4 (█)
     Gray(u8),
     /// Triangle decoration
     Triangle,
@@ -158,90 +163,259 @@ impl Decoration {
 
     /// Generate SVG for this decoration
     pub fn to_svg(&self) -> String {
+        self.to_svg_with(Colors::Variables)
+    }
+
+    /// Generate SVG for this decoration with the given colors
+    pub(crate) fn to_svg_with(&self, colors: Colors) -> String {
+        let mut result = String::new();
+        let _ = self.write_svg(&mut result, colors, &Metrics::default());
+        result
+    }
+
+    /// Write the SVG elements for this decoration to `w`
+    pub(crate) fn write_svg<W: Write>(
+        &self,
+        w: &mut W,
+        colors: Colors,
+        metrics: &Metrics,
+    ) -> fmt::Result {
+        match self.kind {
+            DecorationType::Arrow => self.write_arrow(w, colors, metrics),
+            DecorationType::ClosedPoint => self.write_closed_point(w, colors, metrics),
+            DecorationType::OpenPoint => self.write_open_point(w, colors, metrics),
+            DecorationType::DottedPoint => self.write_dotted_point(w, colors, metrics),
+            DecorationType::ShadedPoint => self.write_shaded_point(w, colors, metrics),
+            DecorationType::XorPoint => self.write_xor_point(w, colors, metrics),
+            DecorationType::Jump(c) => self.write_jump(w, c, colors, metrics),
+            DecorationType::Gray(level) => self.write_gray(w, level, colors, metrics),
+            DecorationType::Triangle => self.write_triangle(w, colors, metrics),
+        }
+    }
+
+    /// Shapes to draw for this decoration
+    pub(crate) fn shapes(&self, metrics: &Metrics) -> Vec<Shape> {
+        let center = metrics.point(self.pos);
+        let angle = metrics.angle(self.angle);
+        let r = metrics.point_radius();
         match self.kind {
-            DecorationType::Arrow => self.arrow_svg(),
-            DecorationType::ClosedPoint => self.closed_point_svg(),
-            DecorationType::OpenPoint => self.open_point_svg(),
-            DecorationType::DottedPoint => self.dotted_point_svg(),
-            DecorationType::ShadedPoint => self.shaded_point_svg(),
-            DecorationType::XorPoint => self.xor_point_svg(),
-            DecorationType::Jump(c) => self.jump_svg(c),
-            DecorationType::Gray(level) => self.gray_svg(level),
-            DecorationType::Triangle => self.triangle_svg(),
+            DecorationType::Arrow => {
+                let points = self.arrow_points(metrics).map(|p| rotate(p, center, angle));
+                vec![Shape::filled(polygon(&points), Paint::Fill)]
+            }
+            DecorationType::ClosedPoint => vec![Shape::filled(circle(center, r), Paint::Fill)],
+            DecorationType::OpenPoint => {
+                vec![Shape::filled(circle(center, r), Paint::Bg).with_stroke(Paint::Stroke)]
+            }
+            DecorationType::DottedPoint => vec![Shape::filled(circle(center, r), Paint::Bg)
+                .with_stroke(Paint::Stroke)
+                .with_dash(0.0, 1.8)],
+            DecorationType::ShadedPoint => {
+                vec![Shape::filled(circle(center, r), Paint::Shade).with_stroke(Paint::Stroke)]
+            }
+            DecorationType::XorPoint => {
+                let line = |a: Vec2, b: Vec2| {
+                    Shape::stroked(vec![Segment::Move(a), Segment::Line(b)], Paint::Stroke)
+                };
+                vec![
+                    Shape::filled(circle(center, r), Paint::Bg).with_stroke(Paint::Stroke),
+                    line(center.offset_pixels(-r, 0.0), center.offset_pixels(r, 0.0)),
+                    line(center.offset_pixels(0.0, -r), center.offset_pixels(0.0, r)),
+                ]
+            }
+            DecorationType::Jump(c) => {
+                let [dn, cdn, cup, up] = self.jump_points(c, metrics);
+                let curve = vec![Segment::Move(dn), Segment::Cubic(cdn, cup, up)];
+                vec![
+                    Shape::stroked(curve.clone(), Paint::Bg).with_width(metrics.length(3.0)),
+                    Shape::stroked(curve, Paint::Stroke),
+                ]
+            }
+            DecorationType::Gray(level) => {
+                let (w, h) = (metrics.cell_width / 2.0, metrics.cell_height / 2.0);
+                let points = [
+                    center.offset_pixels(-w, -h),
+                    center.offset_pixels(w, -h),
+                    center.offset_pixels(w, h),
+                    center.offset_pixels(-w, h),
+                ];
+                vec![Shape::filled(polygon(&points), Paint::Gray(level))]
+            }
+            DecorationType::Triangle => {
+                let (s, h) = (metrics.cell_width / 2.0, metrics.cell_height / 2.0);
+                let points = [
+                    center.offset_pixels(s, 0.0),
+                    center.offset_pixels(-s, -h),
+                    center.offset_pixels(-s, h),
+                ]
+                .map(|p| rotate(p, center, angle));
+                vec![Shape::filled(polygon(&points), Paint::Fill)]
+            }
         }
     }
 
-    fn arrow_svg(&self) -> String {
-        let cx = self.pos.x;
-        let cy = self.pos.y;
+    fn write_arrow<W: Write>(&self, w: &mut W, colors: Colors, metrics: &Metrics) -> fmt::Result {
+        let [tip, back_up, back_dn] = self.arrow_points(metrics);
+        let center = metrics.point(self.pos);
+
+        writeln!(
+            w,
+            "<polygon points=\"{},{} {},{} {},{}\" fill=\"{fill}\" transform=\"rotate({},{},{})\"/>",
+            Coord(tip.x), Coord(tip.y),
+            Coord(back_up.x), Coord(back_up.y),
+            Coord(back_dn.x), Coord(back_dn.y),
+            metrics.angle(self.angle), Coord(center.x), Coord(center.y),
+            fill = colors.fill()
+        )
+    }
+
+    /// Unrotated arrow head: tip, back up, back down
+    fn arrow_points(&self, metrics: &Metrics) -> [Vec2; 3] {
+        let Vec2 { x: cx, y: cy } = metrics.point(self.pos);
+        let (width, height) = (metrics.cell_width, metrics.cell_height);
 
         // Arrow head triangle points in absolute coordinates (matching JS)
         // JS: tip at C.x + 1, back at C.x - 0.5, up/down at C.y ± 0.35
-        // In pixels: tip offset = 1 * SCALE = 8, back offset = -0.5 * SCALE = -4
-        // up/down offset = 0.35 * SCALE * ASPECT = 5.6
-        let tip_x = cx + SCALE;
-        let tip_y = cy;
-        let back_x = cx - 0.5 * SCALE;
-        let back_up_y = cy - 0.35 * SCALE * ASPECT;
-        let back_dn_y = cy + 0.35 * SCALE * ASPECT;
-
-        format!(
-            "<polygon points=\"{},{} {},{} {},{}\" fill=\"var(--aasvg-fill)\" transform=\"rotate({},{},{})\"/>\n",
-            tip_x, tip_y,
-            back_x, back_up_y,
-            back_x, back_dn_y,
-            self.angle, cx, cy
+        // In cells: tip offset = 1 * width, back offset = -0.5 * width,
+        // up/down offset = 0.35 * height (8, -4 and 5.6 at the defaults)
+        let back_x = cx - 0.5 * width;
+        [
+            Vec2::new(cx + width, cy),
+            Vec2::new(back_x, cy - 0.35 * height),
+            Vec2::new(back_x, cy + 0.35 * height),
+        ]
+    }
+
+    fn write_closed_point<W: Write>(
+        &self,
+        w: &mut W,
+        colors: Colors,
+        metrics: &Metrics,
+    ) -> fmt::Result {
+        let center = metrics.point(self.pos);
+        let r = metrics.point_radius();
+        writeln!(
+            w,
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{fill}\"/>",
+            Coord(center.x),
+            Coord(center.y),
+            Coord(r),
+            fill = colors.fill()
         )
     }
 
-    fn closed_point_svg(&self) -> String {
-        let r = SCALE - 2.0;
-        format!(
-            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"var(--aasvg-fill)\"/>\n",
-            self.pos.x, self.pos.y, r
+    fn write_open_point<W: Write>(
+        &self,
+        w: &mut W,
+        colors: Colors,
+        metrics: &Metrics,
+    ) -> fmt::Result {
+        let center = metrics.point(self.pos);
+        let r = metrics.point_radius();
+        writeln!(
+            w,
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{bg}\" stroke=\"{stroke}\"/>",
+            Coord(center.x),
+            Coord(center.y),
+            Coord(r),
+            stroke = colors.stroke(),
+            bg = colors.bg()
         )
     }
 
-    fn open_point_svg(&self) -> String {
-        let r = SCALE - 2.0;
-        format!(
-            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"var(--aasvg-bg)\" stroke=\"var(--aasvg-stroke)\"/>\n",
-            self.pos.x, self.pos.y, r
+    fn write_dotted_point<W: Write>(
+        &self,
+        w: &mut W,
+        colors: Colors,
+        metrics: &Metrics,
+    ) -> fmt::Result {
+        let center = metrics.point(self.pos);
+        let r = metrics.point_radius();
+        writeln!(
+            w,
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{bg}\" stroke=\"{stroke}\" stroke-dasharray=\"0,1.8\"/>",
+            Coord(center.x), Coord(center.y), Coord(r),
+            stroke = colors.stroke(),
+            bg = colors.bg()
         )
     }
 
-    fn dotted_point_svg(&self) -> String {
-        let r = SCALE - 2.0;
-        format!(
-            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"var(--aasvg-bg)\" stroke=\"var(--aasvg-stroke)\" stroke-dasharray=\"0,1.8\"/>\n",
-            self.pos.x, self.pos.y, r
+    fn write_shaded_point<W: Write>(
+        &self,
+        w: &mut W,
+        colors: Colors,
+        metrics: &Metrics,
+    ) -> fmt::Result {
+        let center = metrics.point(self.pos);
+        let r = metrics.point_radius();
+        writeln!(
+            w,
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{shade}\" stroke=\"{stroke}\"/>",
+            Coord(center.x),
+            Coord(center.y),
+            Coord(r),
+            shade = colors.shade(),
+            stroke = colors.stroke()
         )
     }
 
-    fn shaded_point_svg(&self) -> String {
-        let r = SCALE - 2.0;
-        format!(
-            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"#666\" stroke=\"var(--aasvg-stroke)\"/>\n",
-            self.pos.x, self.pos.y, r
+    fn write_xor_point<W: Write>(
+        &self,
+        w: &mut W,
+        colors: Colors,
+        metrics: &Metrics,
+    ) -> fmt::Result {
+        let r = metrics.point_radius();
+        let Vec2 { x: cx, y: cy } = metrics.point(self.pos);
+
+        write!(
+            w,
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{bg}\" stroke=\"{stroke}\"/>\n\
+             <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{stroke}\"/>\n\
+             <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{stroke}\"/>\n",
+            Coord(cx),
+            Coord(cy),
+            Coord(r),
+            Coord(cx - r),
+            Coord(cy),
+            Coord(cx + r),
+            Coord(cy), // Horizontal line through center
+            Coord(cx),
+            Coord(cy - r),
+            Coord(cx),
+            Coord(cy + r), // Vertical line through center
+            stroke = colors.stroke(),
+            bg = colors.bg()
         )
     }
 
-    fn xor_point_svg(&self) -> String {
-        let r = SCALE - 2.0;
-        let cx = self.pos.x;
-        let cy = self.pos.y;
+    fn write_jump<W: Write>(
+        &self,
+        w: &mut W,
+        c: char,
+        colors: Colors,
+        metrics: &Metrics,
+    ) -> fmt::Result {
+        let [dn, cdn, cup, up] = self.jump_points(c, metrics);
 
-        format!(
-            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"var(--aasvg-bg)\" stroke=\"var(--aasvg-stroke)\"/>\n\
-             <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"var(--aasvg-stroke)\"/>\n\
-             <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"var(--aasvg-stroke)\"/>\n",
-            cx, cy, r,
-            cx - r, cy, cx + r, cy,  // Horizontal line through center
-            cx, cy - r, cx, cy + r   // Vertical line through center
+        // JS: 'M ' + dn + 'C ' + cdn + cup + up.coords()
+        // Path goes: dn -> cdn, cup -> up
+        write!(
+            w,
+            "<path d=\"M {},{} C {},{} {},{} {},{}\" fill=\"none\" stroke=\"{bg}\" stroke-width=\"{width}\"/>\n\
+             <path d=\"M {},{} C {},{} {},{} {},{}\" fill=\"none\" stroke=\"{stroke}\"/>\n",
+            Coord(dn.x), Coord(dn.y), Coord(cdn.x), Coord(cdn.y),
+            Coord(cup.x), Coord(cup.y), Coord(up.x), Coord(up.y),
+            Coord(dn.x), Coord(dn.y), Coord(cdn.x), Coord(cdn.y),
+            Coord(cup.x), Coord(cup.y), Coord(up.x), Coord(up.y),
+            stroke = colors.stroke(),
+            bg = colors.bg(),
+            width = Coord(metrics.length(3.0))
         )
     }
 
-    fn jump_svg(&self, c: char) -> String {
+    /// Jump curve: start, two control points, end
+    fn jump_points(&self, c: char, metrics: &Metrics) -> [Vec2; 4] {
         // JS: var dx = (decoration.type === ')') ? +0.75 : -0.75;
         let dx = if c == ')' { 0.75 } else { -0.75 };
 
@@ -254,54 +428,73 @@ impl Decoration {
 
         // JS: up = Vec2(C.x, C.y - 0.5), dn = Vec2(C.x, C.y + 0.5)
         // Vec2.coords() converts to pixel: (x+1)*SCALE, (y+1)*SCALE*ASPECT
-        let up_x = (grid_x + 1.0) * SCALE;
-        let up_y = (grid_y - 0.5 + 1.0) * SCALE * ASPECT;
-        let dn_x = (grid_x + 1.0) * SCALE;
-        let dn_y = (grid_y + 0.5 + 1.0) * SCALE * ASPECT;
+        let up = Vec2::new(
+            (grid_x + 1.0) * SCALE,
+            (grid_y - 0.5 + 1.0) * SCALE * ASPECT,
+        );
+        let dn = Vec2::new(
+            (grid_x + 1.0) * SCALE,
+            (grid_y + 0.5 + 1.0) * SCALE * ASPECT,
+        );
 
         // JS: cup = Vec2(C.x + dx, C.y - 0.5), cdn = Vec2(C.x + dx, C.y + 0.5)
-        let cup_x = (grid_x + dx + 1.0) * SCALE;
-        let cup_y = (grid_y - 0.5 + 1.0) * SCALE * ASPECT;
-        let cdn_x = (grid_x + dx + 1.0) * SCALE;
-        let cdn_y = (grid_y + 0.5 + 1.0) * SCALE * ASPECT;
-
-        // JS: 'M ' + dn + 'C ' + cdn + cup + up.coords()
-        // Path goes: dn -> cdn, cup -> up
-        format!(
-            "<path d=\"M {},{} C {},{} {},{} {},{}\" fill=\"none\" stroke=\"var(--aasvg-bg)\" stroke-width=\"3\"/>\n\
-             <path d=\"M {},{} C {},{} {},{} {},{}\" fill=\"none\" stroke=\"var(--aasvg-stroke)\"/>\n",
-            dn_x, dn_y, cdn_x, cdn_y, cup_x, cup_y, up_x, up_y,
-            dn_x, dn_y, cdn_x, cdn_y, cup_x, cup_y, up_x, up_y
-        )
+        let cup = Vec2::new(
+            (grid_x + dx + 1.0) * SCALE,
+            (grid_y - 0.5 + 1.0) * SCALE * ASPECT,
+        );
+        let cdn = Vec2::new(
+            (grid_x + dx + 1.0) * SCALE,
+            (grid_y + 0.5 + 1.0) * SCALE * ASPECT,
+        );
+
+        [dn, cdn, cup, up].map(|p| metrics.point(p))
     }
 
-    fn gray_svg(&self, level: u8) -> String {
+    fn write_gray<W: Write>(
+        &self,
+        w: &mut W,
+        level: u8,
+        colors: Colors,
+        metrics: &Metrics,
+    ) -> fmt::Result {
         // Gray fill rectangle
-        let x = self.pos.x - SCALE / 2.0;
-        let y = self.pos.y - SCALE * ASPECT / 2.0;
-        let w = SCALE;
-        let h = SCALE * ASPECT;
-
-        format!(
-            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"rgb({},{},{})\"/>\n",
-            x, y, w, h, level, level, level
+        let center = metrics.point(self.pos);
+        let width = metrics.cell_width;
+        let height = metrics.cell_height;
+        let x = center.x - width / 2.0;
+        let y = center.y - height / 2.0;
+
+        writeln!(
+            w,
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+            Coord(x),
+            Coord(y),
+            Coord(width),
+            Coord(height),
+            colors.gray(level)
         )
     }
 
-    fn triangle_svg(&self) -> String {
-        let cx = self.pos.x;
-        let cy = self.pos.y;
-        let s = SCALE / 2.0;
-        let h = SCALE * ASPECT / 2.0;
+    fn write_triangle<W: Write>(
+        &self,
+        w: &mut W,
+        colors: Colors,
+        metrics: &Metrics,
+    ) -> fmt::Result {
+        let Vec2 { x: cx, y: cy } = metrics.point(self.pos);
+        let s = metrics.cell_width / 2.0;
+        let h = metrics.cell_height / 2.0;
 
         // Triangle pointing right, then rotated
-        format!(
-            "<polygon points=\"{},{} {},{} {},{}\" fill=\"var(--aasvg-fill)\" transform=\"translate({},{}) rotate({})\"/>\n",
-            s, 0.0,    // Right point
-            -s, -h,    // Top-left
-            -s, h,     // Bottom-left
-            cx, cy,
-            self.angle
+        writeln!(
+            w,
+            "<polygon points=\"{},{} {},{} {},{}\" fill=\"{fill}\" transform=\"translate({},{}) rotate({})\"/>",
+            Coord(s), 0.0,          // Right point
+            Coord(-s), Coord(-h),   // Top-left
+            Coord(-s), Coord(h),    // Bottom-left
+            Coord(cx), Coord(cy),
+            metrics.angle(self.angle),
+            fill = colors.fill()
         )
     }
 }
@@ -333,7 +526,7 @@ pub fn arrow_angle_back_diagonal_down() -> f64 {
 }
 
 /// Collection of decorations
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct DecorationSet {
     decorations: Vec<Decoration>,
 }
@@ -363,11 +556,27 @@ impl DecorationSet {
 
     /// Generate SVG for all decorations
     pub fn to_svg(&self) -> String {
+        self.to_svg_with(Colors::Variables)
+    }
+
+    /// Generate SVG for all decorations with the given colors
+    pub(crate) fn to_svg_with(&self, colors: Colors) -> String {
         let mut result = String::new();
+        let _ = self.write_svg(&mut result, colors, &Metrics::default());
+        result
+    }
+
+    /// Write the SVG elements for all decorations to `w`
+    pub(crate) fn write_svg<W: Write>(
+        &self,
+        w: &mut W,
+        colors: Colors,
+        metrics: &Metrics,
+    ) -> fmt::Result {
         for decoration in &self.decorations {
-            result.push_str(&decoration.to_svg());
+            decoration.write_svg(w, colors, metrics)?;
         }
-        result
+        Ok(())
     }
 }
 
@@ -406,4 +615,18 @@ mod tests {
         assert!(svg.contains("polygon"));
         assert!(svg.contains("var(--aasvg-fill)"));
     }
+
+    #[test]
+    fn test_point_radius() {
+        let point = Decoration::closed_point(1, 1);
+        assert!(point.to_svg().contains(r#" r="6""#));
+
+        // Narrow cells keep a positive radius
+        let mut svg = String::new();
+        let metrics = Metrics::new(1.0, ASPECT, 13.0);
+        point
+            .write_svg(&mut svg, Colors::Variables, &metrics)
+            .unwrap();
+        assert!(svg.contains(r#" r="0.25""#));
+    }
 }
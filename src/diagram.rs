@@ -0,0 +1,251 @@
+//! Parsed diagram representation.
+//!
+//! A [`Diagram`] is the result of running the path and decoration finders
+//! over an ASCII diagram. It can be inspected or modified before being
+//! rendered, or handed to a different renderer altogether.
+
+use std::{fmt, io};
+
+use crate::decoration::DecorationSet;
+use crate::finder::{find_decorations, find_paths};
+use crate::graph::{build_graph, Graph};
+use crate::grid::{unhide_markers, Grid};
+use crate::path::{Metrics, PathSet, Vec2};
+use crate::region::{find_regions, Region};
+use crate::shape::Shape;
+use crate::svg::{generate_svg, write_svg, write_svg_io, RenderOptions};
+
+/// A run of text left over after paths and decorations were found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextRun {
+    /// Grid column of the first character
+    pub x: i32,
+    /// Grid row
+    pub y: i32,
+    /// The text, with marker characters restored
+    pub text: String,
+}
+
+impl TextRun {
+    /// Number of grid cells covered by this run
+    pub fn len(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    /// Returns true if the run contains no characters
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Pixel position of the baseline center, where the text is anchored
+    pub(crate) fn anchor(&self, metrics: &Metrics) -> Vec2 {
+        let char_count = self.len();
+        Vec2::new(
+            (self.x as f64 + 1.0 + (char_count as f64 - 1.0) / 2.0) * metrics.cell_width,
+            (self.y as f64 + 1.0) * metrics.cell_height + metrics.baseline(),
+        )
+    }
+}
+
+/// A parsed ASCII diagram: paths, decorations and text runs.
+///
+/// Positions are in pixels of the default [`crate::SCALE`] by
+/// [`crate::ASPECT`] cells, whatever the cell size the diagram is rendered
+/// with.
+///
+/// # Example
+///
+/// ```rust
+/// use aasvg::{parse, RenderOptions};
+///
+/// let diagram = parse("+--+\n|Hi|\n+--+");
+/// assert_eq!(diagram.paths.len(), 4);
+/// assert_eq!(diagram.text[0].text, "Hi");
+///
+/// let svg = diagram.to_svg(&RenderOptions::default());
+/// assert!(svg.contains("<text"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Diagram {
+    /// Grid width in characters (longest line)
+    pub width: usize,
+    /// Grid height in characters (number of lines)
+    pub height: usize,
+    /// Lines and curves
+    pub paths: PathSet,
+    /// Arrow heads, points, jumps and fills
+    pub decorations: DecorationSet,
+    /// Closed boxes formed by the paths, in reading order
+    pub regions: Vec<Region>,
+    /// Remaining text, in reading order
+    pub text: Vec<TextRun>,
+    /// Whether the path and decoration finders consumed each cell, row by
+    /// row with `width` cells each
+    pub used: Vec<bool>,
+    /// The characters of each row, without the common indentation and
+    /// trailing spaces
+    pub source: Vec<String>,
+}
+
+impl Diagram {
+    /// Parse an ASCII diagram.
+    ///
+    /// Only `options.spaces` affects parsing; it controls how text is split
+    /// into runs.
+    pub fn parse(input: &str, options: &RenderOptions) -> Self {
+        let mut grid = Grid::new(input);
+        let source = grid.rows();
+        let mut paths = PathSet::new();
+        let mut decorations = DecorationSet::new();
+
+        find_paths(&mut grid, &mut paths);
+        find_decorations(&mut grid, &paths, &mut decorations);
+        let regions = find_regions(&paths);
+        let used = grid.used_cells();
+        let text = extract_text(&mut grid, options.spaces);
+
+        Self {
+            width: grid.width,
+            height: grid.height,
+            paths,
+            decorations,
+            regions,
+            text,
+            used,
+            source,
+        }
+    }
+
+    /// Whether the path and decoration finders consumed the cell at (x, y)
+    pub fn is_used(&self, x: usize, y: usize) -> bool {
+        x < self.width && self.used.get(y * self.width + x) == Some(&true)
+    }
+
+    /// Render the diagram to SVG.
+    pub fn to_svg(&self, options: &RenderOptions) -> String {
+        generate_svg(self, options)
+    }
+
+    /// Write the diagram as SVG to `w`; see [`crate::render_to`].
+    pub fn write_svg<W: fmt::Write>(&self, options: &RenderOptions, w: &mut W) -> fmt::Result {
+        write_svg(w, self, options)
+    }
+
+    /// Write the diagram as SVG to `w`; see [`crate::render_to_io`].
+    pub fn write_svg_io<W: io::Write>(&self, options: &RenderOptions, w: &mut W) -> io::Result<()> {
+        write_svg_io(w, self, options)
+    }
+
+    /// Shapes for box fills if enabled, all paths and then all decorations,
+    /// in drawing order
+    pub(crate) fn shapes(&self, options: &RenderOptions) -> impl Iterator<Item = Shape> + '_ {
+        let boxes = if options.fill_boxes {
+            &self.regions[..]
+        } else {
+            &[]
+        };
+        let metrics = options.metrics();
+        let paths = self.paths.shapes(options.join_paths, &metrics);
+        let decorations = self
+            .decorations
+            .iter()
+            .flat_map(move |d| d.shapes(&metrics));
+        boxes
+            .iter()
+            .map(move |region| region.shape(&metrics))
+            .chain(paths)
+            .chain(decorations)
+    }
+
+    /// Boxes with their labels and the lines connecting them; see [`Graph`].
+    pub fn to_graph(&self) -> Graph {
+        build_graph(self)
+    }
+
+    /// Render the diagram to a TikZ picture; see [`crate::render_tikz`].
+    pub fn to_tikz(&self, options: &RenderOptions) -> String {
+        crate::tikz::generate_tikz(self, options)
+    }
+
+    /// Render the diagram to PNG bytes; see [`crate::render_png`].
+    #[cfg(feature = "png")]
+    pub fn to_png(&self, options: &RenderOptions, scale: f32) -> Result<Vec<u8>, crate::PngError> {
+        crate::png::generate_png(self, options, scale)
+    }
+
+    /// Render the diagram to a PDF page; see [`crate::render_pdf`].
+    #[cfg(feature = "pdf")]
+    pub fn to_pdf(&self, options: &RenderOptions) -> Vec<u8> {
+        crate::pdf::generate_pdf(self, options)
+    }
+}
+
+/// Collect the text that no finder consumed, marking it as used
+fn extract_text(grid: &mut Grid, spaces: u32) -> Vec<TextRun> {
+    let mut runs = Vec::new();
+
+    for y in 0..grid.height as i32 {
+        let mut x = 0;
+        while x < grid.width as i32 {
+            if let Some(start_x) = grid.text_start(x, y, spaces) {
+                let text = grid.extract_text(start_x, y, spaces);
+                x = start_x + text.chars().count() as i32;
+                if !text.is_empty() {
+                    // Restore hidden markers (o, v, V that were part of text)
+                    runs.push(TextRun {
+                        x: start_x,
+                        y,
+                        text: unhide_markers(&text),
+                    });
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoration::DecorationType;
+
+    #[test]
+    fn test_parse_box() {
+        let diagram = Diagram::parse("+--+\n|  |\n+--+", &RenderOptions::new());
+        assert_eq!(diagram.width, 4);
+        assert_eq!(diagram.height, 3);
+        assert!(diagram.paths.len() >= 4);
+        assert!(diagram.text.is_empty());
+    }
+
+    #[test]
+    fn test_parse_text_runs() {
+        let diagram = Diagram::parse("-->  Hello\n     world", &RenderOptions::new());
+        assert_eq!(
+            diagram.text,
+            vec![
+                TextRun {
+                    x: 5,
+                    y: 0,
+                    text: "Hello".to_string()
+                },
+                TextRun {
+                    x: 5,
+                    y: 1,
+                    text: "world".to_string()
+                },
+            ]
+        );
+        assert_eq!(diagram.text[0].len(), 5);
+    }
+
+    #[test]
+    fn test_parse_decorations() {
+        let diagram = Diagram::parse("-->", &RenderOptions::new());
+        let kinds: Vec<_> = diagram.decorations.iter().map(|d| d.kind).collect();
+        assert_eq!(kinds, vec![DecorationType::Arrow]);
+    }
+}
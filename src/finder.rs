@@ -15,6 +15,7 @@ pub fn find_paths(grid: &mut Grid, paths: &mut PathSet) {
     find_backslash_diagonals(grid, paths);
     find_forward_slash_diagonals(grid, paths);
     find_curved_corners(grid, paths);
+    find_rounded_corners(grid, paths);
     find_underscore_lines(grid, paths);
 }
 
@@ -112,6 +113,38 @@ fn is_double_v_line_at(grid: &Grid, x: i32, y: i32) -> bool {
     }
 }
 
+/// Check if a dashed vertical line character at (x,y) is part of a vertical line
+/// Same continuation rules as solid lines, using ':' and the box-drawing dashes
+fn is_dashed_v_line_at(grid: &Grid, x: i32, y: i32) -> bool {
+    let c = grid.get(x, y);
+    let up = grid.get(x, y - 1);
+    let dn = grid.get(x, y + 1);
+
+    if is_dashed_v_line(c) {
+        // Looks like a vertical line...does it continue?
+        is_top_vertex_or_decoration(up)
+            || is_dashed_v_line(up)
+            || is_jump(up)
+            || is_bottom_vertex(dn)
+            || dn == 'v'
+            || dn == 'V'
+            || is_dashed_v_line(dn)
+            || is_jump(dn)
+            || is_point(up)
+            || is_point(dn)
+    } else if is_top_vertex(c) || c == '^' {
+        // May be the top of a vertical line
+        is_dashed_v_line(dn)
+    } else if is_bottom_vertex(c) || c == 'v' || c == 'V' {
+        // May be the bottom of a vertical line
+        is_dashed_v_line(up)
+    } else if is_point(c) {
+        is_dashed_v_line(up) || is_dashed_v_line(dn)
+    } else {
+        false
+    }
+}
+
 /// Stretch vertical line endpoints to meet adjacent lines and decorations
 /// Returns (adjusted_start_y, adjusted_end_y) as fractional grid coordinates
 /// This implements the JS stretching logic from markdeep-diagram.js lines 847-864
@@ -211,15 +244,18 @@ fn stretch_v_line_endpoints(
     (a, b)
 }
 
-/// Find all vertical lines (solid and double), checking both at each position
+/// Find all vertical lines (solid, double and dashed), checking each at every position
 /// This matches JS behavior where solid is tried first, then double, at each (x, y)
 /// See markdeep-diagram.js lines 833-882
 fn find_vertical_lines(grid: &mut Grid, paths: &mut PathSet) {
     for x in 0..grid.width as i32 {
         let mut y = 0;
         while y < grid.height as i32 {
-            // Try solid first, then double (matching JS order)
-            if try_vline(grid, paths, x, &mut y, false) || try_vline(grid, paths, x, &mut y, true) {
+            // Try solid first, then double (matching JS order), then dashed
+            if try_vline(grid, paths, x, &mut y, VLineType::Solid)
+                || try_vline(grid, paths, x, &mut y, VLineType::Double)
+                || try_vline(grid, paths, x, &mut y, VLineType::Dashed)
+            {
                 // Line was found and processed, continue to next position
                 // (y was already updated by try_vline)
                 continue;
@@ -229,29 +265,57 @@ fn find_vertical_lines(grid: &mut Grid, paths: &mut PathSet) {
     }
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum VLineType {
+    Solid,
+    Double,
+    Dashed,
+}
+
 /// Try to find and process a vertical line at (x, y)
 /// Returns true if a line was found, false otherwise
 /// If a line is found, y is updated to point to the position after the line
-/// `is_double` controls whether we're looking for solid (false) or double (true) lines
-fn try_vline(grid: &mut Grid, paths: &mut PathSet, x: i32, y: &mut i32, is_double: bool) -> bool {
-    let check_fn = if is_double {
-        is_double_v_line_at
-    } else {
-        is_solid_v_line_at
+fn try_vline(
+    grid: &mut Grid,
+    paths: &mut PathSet,
+    x: i32,
+    y: &mut i32,
+    line_type: VLineType,
+) -> bool {
+    let check_fn = match line_type {
+        VLineType::Solid => is_solid_v_line_at,
+        VLineType::Double => is_double_v_line_at,
+        VLineType::Dashed => is_dashed_v_line_at,
+    };
+    let is_line = match line_type {
+        VLineType::Solid => is_solid_v_line,
+        VLineType::Double => is_double_v_line,
+        VLineType::Dashed => is_dashed_v_line,
     };
 
-    if !check_fn(grid, x, *y) || grid.is_used(x, *y) {
+    if !check_fn(grid, x, *y) || grid.is_used(x, *y) || !box_arm_meets_v_line(grid, x, *y, is_line)
+    {
         return false;
     }
 
     // This character begins a vertical line...find the end
     let start_y = *y;
 
+    // A run made only of ':' is punctuation in stacked text ("a:" over "b:")
+    // unless it meets a vertex, arrow or horizontal line at one of its ends
+    if line_type == VLineType::Dashed && !is_anchored_colon_run(grid, x, start_y) {
+        return false;
+    }
+
     // Mark cells as used and advance y while the line continues
     loop {
         grid.set_used(x, *y);
         *y += 1;
-        if *y >= grid.height as i32 || !check_fn(grid, x, *y) {
+        if *y >= grid.height as i32
+            || !check_fn(grid, x, *y)
+            || !box_joins_v(grid.get(x, *y - 1), grid.get(x, *y))
+            || weight_changes_v(grid.get(x, *y - 1), grid.get(x, *y))
+        {
             break;
         }
     }
@@ -259,7 +323,17 @@ fn try_vline(grid: &mut Grid, paths: &mut PathSet, x: i32, y: &mut i32, is_doubl
     let end_y = *y - 1;
 
     // Apply stretching logic
-    let (adj_start_y, adj_end_y) = stretch_v_line_endpoints(grid, x, start_y, end_y, is_double);
+    let is_double = line_type == VLineType::Double;
+    let (mut adj_start_y, mut adj_end_y) =
+        stretch_v_line_endpoints(grid, x, start_y, end_y, is_double);
+
+    // Rounded corners draw their own arc over the last half cell
+    if is_rounded_corner(grid.get(x, start_y)) {
+        adj_start_y += 0.5;
+    }
+    if is_rounded_corner(grid.get(x, end_y)) {
+        adj_end_y -= 0.5;
+    }
 
     // Don't insert degenerate lines (JS: if ((A.x !== B.x) || (A.y !== B.y)))
     if adj_start_y != adj_end_y {
@@ -267,8 +341,30 @@ fn try_vline(grid: &mut Grid, paths: &mut PathSet, x: i32, y: &mut i32, is_doubl
             Vec2::from_grid_frac(x as f64, adj_start_y),
             Vec2::from_grid_frac(x as f64, adj_end_y),
         );
-        if is_double {
-            path = path.with_double(true);
+        match line_type {
+            VLineType::Solid => {
+                // Weight is taken from the joints inside the run, so a
+                // half-heavy end character like `╽` does not thicken it
+                let heavy = (start_y..end_y).any(|y| v_arms(grid.get(x, y)).1 == Arm::Heavy);
+                path = path.with_thick(heavy);
+            }
+            VLineType::Double => {
+                // Only ends on the corner's center meet its rails
+                let miter = |y: i32, adj_y: f64| {
+                    if adj_y == y as f64 {
+                        double_v_miter(grid.get(x, y))
+                    } else {
+                        0.0
+                    }
+                };
+                path = path
+                    .with_double(true)
+                    .with_miter(miter(start_y, adj_start_y), miter(end_y, adj_end_y));
+            }
+            VLineType::Dashed => {
+                let heavy = (start_y..=end_y).any(|y| is_heavy_dashed_line(grid.get(x, y)));
+                path = path.with_dashed(true).with_thick(heavy);
+            }
         }
         paths.insert(path);
     }
@@ -277,6 +373,49 @@ fn try_vline(grid: &mut Grid, paths: &mut PathSet, x: i32, y: &mut i32, is_doubl
     true
 }
 
+/// Miter of a double vertical line ending on `c`: 1 if its right rail runs
+/// along the outside of a double corner such as `╗`, -1 if along the inside
+fn double_v_miter(c: char) -> f64 {
+    match box_arms(c) {
+        Some(arms) if arms.is_double_corner() => {
+            if arms.right.is_double() {
+                -1.0
+            } else {
+                1.0
+            }
+        }
+        _ => 0.0,
+    }
+}
+
+/// Check that a box-drawing character at (x, y) reaches a line of the same
+/// kind through one of its own vertical arms (so `┘` over `┐` stays apart).
+/// Any other character passes.
+fn box_arm_meets_v_line(grid: &Grid, x: i32, y: i32, is_line: fn(char) -> bool) -> bool {
+    let c = grid.get(x, y);
+    if box_arms(c).is_none() {
+        return true;
+    }
+    let up = grid.get(x, y - 1);
+    let dn = grid.get(x, y + 1);
+    (box_joins_v(up, c) && (is_line(up) || is_line_end(up)))
+        || (box_joins_v(c, dn) && (is_line(dn) || is_line_end(dn)))
+}
+
+/// Check whether the dashed vertical run starting at (x, start_y) is a line
+/// rather than a column of colons in text
+fn is_anchored_colon_run(grid: &Grid, x: i32, start_y: i32) -> bool {
+    let mut y = start_y;
+    while y < grid.height as i32 && is_dashed_v_line_at(grid, x, y) {
+        if grid.get(x, y) != ':' {
+            return true;
+        }
+        y += 1;
+    }
+
+    is_solid_h_line(grid.get(x, start_y - 1)) || is_solid_h_line(grid.get(x, y))
+}
+
 /// Find special short vertical lines for circuit diagrams
 /// JS markdeep-diagram.js lines 886-916
 fn find_circuit_diagram_short_lines(grid: &mut Grid, paths: &mut PathSet) {
@@ -420,11 +559,12 @@ fn is_solid_v_line_or_jump_or_point(c: char) -> bool {
     is_solid_v_line(c) || is_jump(c) || is_point(c)
 }
 
-/// Check if any horizontal line (solid, squiggle, or double) is at the position
+/// Check if any horizontal line (solid, squiggle, double, or dashed) is at the position
 fn is_any_h_line_at(grid: &Grid, x: i32, y: i32) -> bool {
     is_solid_h_line_at(grid, x, y)
         || is_squiggle_h_line_at(grid, x, y)
         || is_double_h_line_at(grid, x, y)
+        || is_dashed_h_line_at(grid, x, y)
 }
 
 /// Check if position is part of a solid horizontal line
@@ -442,17 +582,16 @@ fn is_solid_h_line_at(grid: &Grid, x: i32, y: i32) -> bool {
         // Looks like a horizontal line...does it continue? We need three in a row.
         if is_solid_h_line(lt) {
             // Has line char to left - need line or vertex to right, or line to far left
-            return is_solid_h_line(rt)
+            is_solid_h_line(rt)
                 || is_vertex_or_right_decoration(rt)
                 || is_solid_h_line(ltlt)
-                || is_vertex_or_left_decoration(ltlt);
+                || is_vertex_or_left_decoration(ltlt)
         } else if is_vertex_or_left_decoration(lt) {
             // Vertex to left - need line char to right
-            return is_solid_h_line(rt);
+            is_solid_h_line(rt)
         } else {
             // Need line to right AND (line or vertex at far right)
-            return is_solid_h_line(rt)
-                && (is_solid_h_line(rtrt) || is_vertex_or_right_decoration(rtrt));
+            is_solid_h_line(rt) && (is_solid_h_line(rtrt) || is_vertex_or_right_decoration(rtrt))
         }
     } else if c == '<' {
         // Left arrow is part of solid line if there are two line chars to the right
@@ -469,17 +608,18 @@ fn is_solid_h_line_at(grid: &Grid, x: i32, y: i32) -> bool {
     }
 }
 
-/// Find all horizontal lines (solid, squiggle, and double), checking all at each position
+/// Find all horizontal lines (solid, squiggle, double, and dashed), checking all at each position
 /// This matches JS behavior where solid is tried first, then squiggle, then double
 /// See markdeep-diagram.js lines 924-979
 fn find_horizontal_lines(grid: &mut Grid, paths: &mut PathSet) {
     for y in 0..grid.height as i32 {
         let mut x = 0;
         while x < grid.width as i32 {
-            // Try solid first, then squiggle, then double (matching JS order)
+            // Try solid first, then squiggle, then double (matching JS order), then dashed
             if try_hline(grid, paths, &mut x, y, HLineType::Solid)
                 || try_hline(grid, paths, &mut x, y, HLineType::Squiggle)
                 || try_hline(grid, paths, &mut x, y, HLineType::Double)
+                || try_hline(grid, paths, &mut x, y, HLineType::Dashed)
             {
                 // Line was found and processed, continue to next position
                 continue;
@@ -494,6 +634,7 @@ enum HLineType {
     Solid,
     Squiggle,
     Double,
+    Dashed,
 }
 
 /// Try to find and process a horizontal line at (x, y)
@@ -509,9 +650,16 @@ fn try_hline(
         HLineType::Solid => is_solid_h_line_at,
         HLineType::Squiggle => is_squiggle_h_line_at,
         HLineType::Double => is_double_h_line_at,
+        HLineType::Dashed => is_dashed_h_line_at,
+    };
+    let is_line = match line_type {
+        HLineType::Solid => is_solid_h_line,
+        HLineType::Squiggle => is_squiggle_h_line,
+        HLineType::Double => is_double_h_line,
+        HLineType::Dashed => is_dashed_h_line,
     };
 
-    if !check_fn(grid, *x, y) {
+    if !check_fn(grid, *x, y) || !box_arm_meets_h_line(grid, *x, y, is_line) {
         return false;
     }
 
@@ -522,7 +670,11 @@ fn try_hline(
     loop {
         grid.set_used(*x, y);
         *x += 1;
-        if *x >= grid.width as i32 || !check_fn(grid, *x, y) {
+        if *x >= grid.width as i32
+            || !check_fn(grid, *x, y)
+            || !box_joins_h(grid.get(*x - 1, y), grid.get(*x, y))
+            || weight_changes_h(grid.get(*x - 1, y), grid.get(*x, y))
+        {
             break;
         }
     }
@@ -530,7 +682,15 @@ fn try_hline(
     let end_x = *x - 1;
 
     // Apply stretching logic
-    let (adj_start, adj_end) = stretch_h_line_endpoints(grid, start_x, end_x, y);
+    let (mut adj_start, mut adj_end) = stretch_h_line_endpoints(grid, start_x, end_x, y);
+
+    // Rounded corners draw their own arc over the last half cell
+    if is_rounded_corner(grid.get(start_x, y)) {
+        adj_start += 0.5;
+    }
+    if is_rounded_corner(grid.get(end_x, y)) {
+        adj_end -= 0.5;
+    }
 
     // Only insert non-degenerate lines (JS: if ((A.x !== B.x) || (A.y !== B.y)))
     if adj_start != adj_end {
@@ -539,9 +699,30 @@ fn try_hline(
             Vec2::from_grid_frac(adj_end, y as f64),
         );
         match line_type {
-            HLineType::Solid => {}
+            HLineType::Solid => {
+                // Weight is taken from the joints inside the run, so a
+                // half-heavy end character like `╼` does not thicken it
+                let heavy = (start_x..end_x).any(|x| h_arms(grid.get(x, y)).1 == Arm::Heavy);
+                path = path.with_thick(heavy);
+            }
             HLineType::Squiggle => path = path.with_squiggle(true),
-            HLineType::Double => path = path.with_double(true),
+            HLineType::Double => {
+                // Only ends on the corner's center meet its rails
+                let miter = |x: i32, adj_x: f64| {
+                    if adj_x == x as f64 {
+                        double_h_miter(grid.get(x, y))
+                    } else {
+                        0.0
+                    }
+                };
+                path = path
+                    .with_double(true)
+                    .with_miter(miter(start_x, adj_start), miter(end_x, adj_end));
+            }
+            HLineType::Dashed => {
+                let heavy = (start_x..=end_x).any(|x| is_heavy_dashed_line(grid.get(x, y)));
+                path = path.with_dashed(true).with_thick(heavy);
+            }
         }
         paths.insert(path);
         return true;
@@ -551,6 +732,40 @@ fn try_hline(
     true
 }
 
+/// Miter of a double horizontal line ending on `c`: 1 if its upper rail
+/// runs along the outside of a double corner such as `╔`, -1 if along the
+/// inside
+fn double_h_miter(c: char) -> f64 {
+    match box_arms(c) {
+        Some(arms) if arms.is_double_corner() => {
+            if arms.down.is_double() {
+                1.0
+            } else {
+                -1.0
+            }
+        }
+        _ => 0.0,
+    }
+}
+
+/// Check that a box-drawing character at (x, y) reaches a line of the same
+/// kind through one of its own horizontal arms. Any other character passes.
+fn box_arm_meets_h_line(grid: &Grid, x: i32, y: i32, is_line: fn(char) -> bool) -> bool {
+    let c = grid.get(x, y);
+    if box_arms(c).is_none() {
+        return true;
+    }
+    let lt = grid.get(x - 1, y);
+    let rt = grid.get(x + 1, y);
+    (box_joins_h(lt, c) && (is_line(lt) || is_line_end(lt)))
+        || (box_joins_h(c, rt) && (is_line(rt) || is_line_end(rt)))
+}
+
+/// Check if a character can terminate a line: a vertex, arrow head, point or jump
+fn is_line_end(c: char) -> bool {
+    is_vertex(c) || is_arrow_head(c) || is_point(c) || is_jump(c)
+}
+
 /// Check if position is part of a squiggle horizontal line
 /// Following JS logic from isHLineAt
 fn is_squiggle_h_line_at(grid: &Grid, x: i32, y: i32) -> bool {
@@ -565,15 +780,14 @@ fn is_squiggle_h_line_at(grid: &Grid, x: i32, y: i32) -> bool {
     if (is_squiggle_h_line(c) && c != '+') || (is_squiggle_h_line(lt) && is_jump(c)) {
         // Looks like a horizontal line...does it continue? We need three in a row.
         if is_squiggle_h_line(lt) {
-            return is_squiggle_h_line(rt)
+            is_squiggle_h_line(rt)
                 || is_vertex_or_right_decoration(rt)
                 || is_squiggle_h_line(ltlt)
-                || is_vertex_or_left_decoration(ltlt);
+                || is_vertex_or_left_decoration(ltlt)
         } else if is_vertex_or_left_decoration(lt) {
-            return is_squiggle_h_line(rt);
+            is_squiggle_h_line(rt)
         } else {
-            return is_squiggle_h_line(rt)
-                && (is_squiggle_h_line(rtrt) || is_vertex_or_right_decoration(rtrt));
+            is_squiggle_h_line(rt) && (is_squiggle_h_line(rtrt) || is_vertex_or_right_decoration(rtrt))
         }
     } else if c == '<' {
         is_squiggle_h_line(rt) && is_squiggle_h_line(rtrt)
@@ -603,15 +817,14 @@ fn is_double_h_line_at(grid: &Grid, x: i32, y: i32) -> bool {
     {
         // Looks like a horizontal line...does it continue? We need three in a row.
         if is_double_h_line(lt) {
-            return is_double_h_line(rt)
+            is_double_h_line(rt)
                 || is_vertex_or_right_decoration(rt)
                 || is_double_h_line(ltlt)
-                || is_vertex_or_left_decoration(ltlt);
+                || is_vertex_or_left_decoration(ltlt)
         } else if is_vertex_or_left_decoration(lt) {
-            return is_double_h_line(rt);
+            is_double_h_line(rt)
         } else {
-            return is_double_h_line(rt)
-                && (is_double_h_line(rtrt) || is_vertex_or_right_decoration(rtrt));
+            is_double_h_line(rt) && (is_double_h_line(rtrt) || is_vertex_or_right_decoration(rtrt))
         }
     } else if c == '<' {
         is_double_h_line(rt) && is_double_h_line(rtrt)
@@ -625,6 +838,42 @@ fn is_double_h_line_at(grid: &Grid, x: i32, y: i32) -> bool {
     }
 }
 
+/// Check if position is part of a dashed horizontal line
+/// Same rules as squiggle lines, using the box-drawing dash characters
+fn is_dashed_h_line_at(grid: &Grid, x: i32, y: i32) -> bool {
+    let c = grid.get(x, y);
+
+    let lt = grid.get(x - 1, y);
+    let ltlt = grid.get(x - 2, y);
+    let rt = grid.get(x + 1, y);
+    let rtrt = grid.get(x + 2, y);
+
+    if (is_dashed_h_line(c) && !is_vertex(c) && !is_jump(c))
+        || (is_dashed_h_line(lt) && is_jump(c))
+    {
+        // Looks like a horizontal line...does it continue? We need three in a row.
+        if is_dashed_h_line(lt) {
+            is_dashed_h_line(rt)
+                || is_vertex_or_right_decoration(rt)
+                || is_dashed_h_line(ltlt)
+                || is_vertex_or_left_decoration(ltlt)
+        } else if is_vertex_or_left_decoration(lt) {
+            is_dashed_h_line(rt)
+        } else {
+            is_dashed_h_line(rt) && (is_dashed_h_line(rtrt) || is_vertex_or_right_decoration(rtrt))
+        }
+    } else if c == '<' {
+        is_dashed_h_line(rt) && is_dashed_h_line(rtrt)
+    } else if c == '>' {
+        is_dashed_h_line(lt) && is_dashed_h_line(ltlt)
+    } else if is_vertex(c) {
+        (is_dashed_h_line(lt) && is_dashed_h_line(ltlt))
+            || (is_dashed_h_line(rt) && is_dashed_h_line(rtrt))
+    } else {
+        false
+    }
+}
+
 // ============================================================================
 // Diagonal line finding
 // ============================================================================
@@ -936,13 +1185,14 @@ fn find_forward_slash_diagonals(grid: &mut Grid, paths: &mut PathSet) {
 // Curved corner finding
 // ============================================================================
 
+/// Bezier circle approximation constant
+/// https://spencermortensen.com/articles/bezier-circle/
+const CURVE: f64 = 0.551915024494;
+
 fn find_curved_corners(grid: &mut Grid, paths: &mut PathSet) {
     let width = grid.width as i32;
     let height = grid.height as i32;
 
-    // Bezier circle approximation constant
-    // https://spencermortensen.com/articles/bezier-circle/
-    const CURVE: f64 = 0.551915024494;
     const CURVE_X: f64 = 2.0 * CURVE;
     const CURVE_Y: f64 = CURVE;
 
@@ -1091,6 +1341,34 @@ fn find_curved_corners(grid: &mut Grid, paths: &mut PathSet) {
     }
 }
 
+/// Draw the quarter arcs of rounded box-drawing corners (╭╮╯╰)
+/// The lines meeting a corner stop half a cell short of its center, and the
+/// arc joins their ends. Corners no line reached are left as text.
+fn find_rounded_corners(grid: &mut Grid, paths: &mut PathSet) {
+    for y in 0..grid.height as i32 {
+        for x in 0..grid.width as i32 {
+            let c = grid.get(x, y);
+            if !is_rounded_corner(c) || !grid.is_used(x, y) {
+                continue;
+            }
+            let Some(arms) = box_arms(c) else {
+                continue;
+            };
+
+            // Direction of the horizontal and vertical arms
+            let dx = if arms.right != Arm::None { 0.5 } else { -0.5 };
+            let dy = if arms.down != Arm::None { 0.5 } else { -0.5 };
+            let (x, y) = (x as f64, y as f64);
+
+            let start = Vec2::from_grid_frac(x + dx, y);
+            let end = Vec2::from_grid_frac(x, y + dy);
+            let ctrl1 = Vec2::from_grid_frac(x + dx * (1.0 - CURVE), y);
+            let ctrl2 = Vec2::from_grid_frac(x, y + dy * (1.0 - CURVE));
+            paths.insert(Path::curve(start, end, ctrl1, ctrl2));
+        }
+    }
+}
+
 // ============================================================================
 // Underscore line finding
 // ============================================================================
@@ -1311,6 +1589,10 @@ fn find_points(grid: &mut Grid, paths: &PathSet, decorations: &mut DecorationSet
                 || is_solid_h_line(grid.get(x + 1, y))
                 || is_solid_v_line(grid.get(x, y - 1))
                 || is_solid_v_line(grid.get(x, y + 1))
+                || is_dashed_h_line(grid.get(x - 1, y))
+                || is_dashed_h_line(grid.get(x + 1, y))
+                || is_dashed_v_line(grid.get(x, y - 1))
+                || is_dashed_v_line(grid.get(x, y + 1))
                 || is_solid_d_line(grid.get(x - 1, y + 1))
                 || is_solid_d_line(grid.get(x + 1, y - 1))
                 || is_solid_b_line(grid.get(x - 1, y - 1))
@@ -1328,17 +1610,13 @@ fn find_points(grid: &mut Grid, paths: &PathSet, decorations: &mut DecorationSet
             let is_on_line = on_line(grid, x, y);
 
             match c {
-                '*' => {
-                    if adjacent_to_line || path_ends_here || is_on_line {
-                        decorations.insert(Decoration::closed_point(x, y));
-                        grid.set_used(x, y);
-                    }
+                '*' if adjacent_to_line || path_ends_here || is_on_line => {
+                    decorations.insert(Decoration::closed_point(x, y));
+                    grid.set_used(x, y);
                 }
-                'o' => {
-                    if adjacent_to_line || path_ends_here || is_on_line {
-                        decorations.insert(Decoration::open_point(x, y));
-                        grid.set_used(x, y);
-                    }
+                'o' if adjacent_to_line || path_ends_here || is_on_line => {
+                    decorations.insert(Decoration::open_point(x, y));
+                    grid.set_used(x, y);
                 }
                 '◌' => {
                     decorations.insert(Decoration::dotted_point(x, y));
@@ -1384,10 +1662,12 @@ fn find_jumps(grid: &mut Grid, paths: &PathSet, decorations: &mut DecorationSet)
                 // Either via paths or direct character check
                 let has_line_above = paths.down_ends_at(x, y)
                     || is_solid_v_line(grid.get(x, y - 1))
-                    || is_double_v_line(grid.get(x, y - 1));
+                    || is_double_v_line(grid.get(x, y - 1))
+                    || is_dashed_v_line(grid.get(x, y - 1));
                 let has_line_below = paths.up_ends_at(x, y)
                     || is_solid_v_line(grid.get(x, y + 1))
-                    || is_double_v_line(grid.get(x, y + 1));
+                    || is_double_v_line(grid.get(x, y + 1))
+                    || is_dashed_v_line(grid.get(x, y + 1));
 
                 if has_line_above && has_line_below {
                     decorations.insert(Decoration::jump(x, y, c));
@@ -1475,11 +1755,102 @@ mod tests {
         assert_eq!(decorations.len(), 1);
     }
 
+    #[test]
+    fn test_find_dashed_lines() {
+        let mut grid = Grid::new("┄┄┄┄\n\n┆\n┆");
+        let mut paths = PathSet::new();
+        find_paths(&mut grid, &mut paths);
+        assert_eq!(paths.len(), 2);
+        assert!(paths.iter().all(|p| p.style.dashed));
+    }
+
+    #[test]
+    fn test_find_colon_dashed_line() {
+        let mut grid = Grid::new("+\n:\n:\nv");
+        let mut paths = PathSet::new();
+        let mut decorations = DecorationSet::new();
+        find_paths(&mut grid, &mut paths);
+        find_decorations(&mut grid, &paths, &mut decorations);
+        assert_eq!(paths.len(), 1);
+        assert!(paths.iter().next().unwrap().style.dashed);
+        assert_eq!(decorations.len(), 1);
+    }
+
+    #[test]
+    fn test_stacked_colons_are_text() {
+        let mut grid = Grid::new("a: 1\nb: 2");
+        let mut paths = PathSet::new();
+        find_paths(&mut grid, &mut paths);
+        assert!(paths.is_empty());
+    }
+
+    #[test]
+    fn test_find_box_drawing() {
+        let mut grid = Grid::new("┌──┬──┐\n│  │  │\n└──┴──┘");
+        let mut paths = PathSet::new();
+        find_paths(&mut grid, &mut paths);
+        // Two horizontal lines and three vertical lines, all meeting at cell centers
+        assert_eq!(paths.len(), 5);
+        assert!(paths.iter().all(|p| !p.is_curved()));
+        assert!(paths
+            .iter()
+            .any(|p| p.a == Vec2::from_grid(0, 0) && p.b == Vec2::from_grid(6, 0)));
+        assert!(paths
+            .iter()
+            .any(|p| p.a == Vec2::from_grid(3, 0) && p.b == Vec2::from_grid(3, 2)));
+    }
+
+    #[test]
+    fn test_find_rounded_corners() {
+        let mut grid = Grid::new("╭──╮\n│  │\n╰──╯");
+        let mut paths = PathSet::new();
+        find_paths(&mut grid, &mut paths);
+        // Four straight sides stopping short of the corners, plus four arcs
+        assert_eq!(paths.len(), 8);
+        assert_eq!(paths.iter().filter(|p| p.is_curved()).count(), 4);
+        assert!(paths.iter().any(
+            |p| p.a == Vec2::from_grid_frac(0.5, 0.0) && p.b == Vec2::from_grid_frac(2.5, 0.0)
+        ));
+    }
+
+    #[test]
+    fn test_box_drawing_arms_keep_lines_apart() {
+        // The corners face away from each other, so the dashed side stays
+        // separate from the solid box above it
+        let mut grid = Grid::new("└──┘\n┌┄┄┐\n┆  ┆\n└┄┄┘");
+        let mut paths = PathSet::new();
+        find_paths(&mut grid, &mut paths);
+        let dashed: Vec<_> = paths.iter().filter(|p| p.style.dashed).collect();
+        assert_eq!(dashed.len(), 4);
+        assert!(dashed
+            .iter()
+            .any(|p| p.a == Vec2::from_grid(3, 1) && p.b == Vec2::from_grid(3, 3)));
+    }
+
+    #[test]
+    fn test_find_mixed_box_drawing() {
+        let mut grid = Grid::new("╒══╕\n│  │\n╘══╛");
+        let mut paths = PathSet::new();
+        find_paths(&mut grid, &mut paths);
+        assert_eq!(paths.len(), 4);
+        assert_eq!(paths.iter().filter(|p| p.style.double).count(), 2);
+    }
+
+    #[test]
+    fn test_find_heavy_lines() {
+        let mut grid = Grid::new("┏━━┓\n┃  ┃\n┗━━┛\n──━━");
+        let mut paths = PathSet::new();
+        find_paths(&mut grid, &mut paths);
+        // The light/heavy line at the bottom is split where its weight changes
+        assert_eq!(paths.len(), 6);
+        assert_eq!(paths.iter().filter(|p| p.style.thick).count(), 5);
+    }
+
     #[test]
     fn test_find_diagonal() {
         let mut grid = Grid::new("\\\n \\");
         let mut paths = PathSet::new();
         find_paths(&mut grid, &mut paths);
-        assert!(paths.len() >= 1);
+        assert!(!paths.is_empty());
     }
 }
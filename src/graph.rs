@@ -0,0 +1,626 @@
+//! Semantic graph of a diagram: boxes and the lines connecting them.
+//!
+//! Nodes are the closed [`Region`]s, labelled with the text inside them.
+//! Edges are chains of lines leading from one box to another, directed by
+//! the arrow heads at their ends. The graph describes what a diagram
+//! connects rather than how it looks, and converts to JSON, Graphviz DOT or
+//! a Mermaid flowchart for tools that lay it out again.
+
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+use crate::decoration::DecorationType;
+use crate::diagram::{Diagram, TextRun};
+use crate::path::{Coord, Vec2, ASPECT, SCALE};
+use crate::region::{region_faces, Planar, Region};
+
+/// Arrow heads on an [`Edge`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// A plain line
+    None,
+    /// An arrow head at `to`
+    Forward,
+    /// Arrow heads at both ends
+    Both,
+}
+
+impl Direction {
+    fn name(self) -> &'static str {
+        match self {
+            Direction::None => "none",
+            Direction::Forward => "forward",
+            Direction::Both => "both",
+        }
+    }
+}
+
+/// A box and the text inside it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    /// Text runs inside the box; runs on one row are joined by a space and
+    /// rows by a newline
+    pub label: String,
+    /// The box
+    pub region: Region,
+}
+
+/// A chain of lines connecting two boxes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    /// Index of the node the line starts at
+    pub from: usize,
+    /// Index of the node the line leads to
+    pub to: usize,
+    pub direction: Direction,
+}
+
+/// Boxes and the connections between them.
+///
+/// # Example
+///
+/// ```rust
+/// use aasvg::{parse, Direction};
+///
+/// let graph = parse("+---+    +---+\n| A |--->| B |\n+---+    +---+").to_graph();
+/// assert_eq!(graph.nodes[0].label, "A");
+/// assert_eq!(graph.nodes[1].label, "B");
+/// assert_eq!(graph.edges[0].from, 0);
+/// assert_eq!(graph.edges[0].to, 1);
+/// assert_eq!(graph.edges[0].direction, Direction::Forward);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Graph {
+    /// Boxes, in reading order
+    pub nodes: Vec<Node>,
+    /// Connections, indexing into `nodes` and sorted by them
+    pub edges: Vec<Edge>,
+}
+
+impl Graph {
+    /// Serialize as JSON: nodes with their label and grid position, and
+    /// edges between node ids.
+    ///
+    /// ```json
+    /// {
+    ///   "nodes": [
+    ///     {"id": 0, "label": "A", "x": 0, "y": 0, "width": 4, "height": 2, "rounded": false}
+    ///   ],
+    ///   "edges": [
+    ///     {"from": 0, "to": 1, "direction": "forward"}
+    ///   ]
+    /// }
+    /// ```
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        let _ = self.write_json(&mut json);
+        json
+    }
+
+    fn write_json<W: Write>(&self, w: &mut W) -> fmt::Result {
+        w.write_str("{\n  \"nodes\": [")?;
+        for (id, node) in self.nodes.iter().enumerate() {
+            let (min, max) = (node.region.min, node.region.max);
+            w.write_str(if id == 0 { "\n" } else { ",\n" })?;
+            write!(
+                w,
+                "    {{\"id\": {}, \"label\": {}, \"x\": {}, \"y\": {}, \"width\": {}, \"height\": {}, \"rounded\": {}}}",
+                id,
+                Json(&node.label),
+                Coord(min.x / SCALE - 1.0),
+                Coord(min.y / (SCALE * ASPECT) - 1.0),
+                Coord((max.x - min.x) / SCALE),
+                Coord((max.y - min.y) / (SCALE * ASPECT)),
+                node.region.is_rounded()
+            )?;
+        }
+        if !self.nodes.is_empty() {
+            w.write_str("\n  ")?;
+        }
+        w.write_str("],\n  \"edges\": [")?;
+        for (i, edge) in self.edges.iter().enumerate() {
+            w.write_str(if i == 0 { "\n" } else { ",\n" })?;
+            write!(
+                w,
+                "    {{\"from\": {}, \"to\": {}, \"direction\": \"{}\"}}",
+                edge.from,
+                edge.to,
+                edge.direction.name()
+            )?;
+        }
+        if !self.edges.is_empty() {
+            w.write_str("\n  ")?;
+        }
+        w.write_str("]\n}\n")
+    }
+
+    /// Convert to a Graphviz DOT `digraph`, leaving the layout to Graphviz.
+    ///
+    /// Nodes are named `n0`, `n1`, … after their index and drawn as boxes,
+    /// rounded where the diagram's are. Edges without arrow heads get
+    /// `dir=none` and double-headed ones `dir=both`.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        let _ = self.write_dot(&mut dot);
+        dot
+    }
+
+    fn write_dot<W: Write>(&self, w: &mut W) -> fmt::Result {
+        w.write_str("digraph {\n  node [shape=box];\n")?;
+        for (id, node) in self.nodes.iter().enumerate() {
+            write!(w, "  n{} [label={}", id, Dot(&node.label))?;
+            if node.region.is_rounded() {
+                w.write_str(", style=rounded")?;
+            }
+            w.write_str("];\n")?;
+        }
+        for edge in &self.edges {
+            write!(w, "  n{} -> n{}", edge.from, edge.to)?;
+            match edge.direction {
+                Direction::None => w.write_str(" [dir=none]")?,
+                Direction::Forward => {}
+                Direction::Both => w.write_str(" [dir=both]")?,
+            }
+            w.write_str(";\n")?;
+        }
+        w.write_str("}\n")
+    }
+
+    /// Convert to a Mermaid `flowchart`, leaving the layout to Mermaid.
+    ///
+    /// Nodes are named `n0`, `n1`, … after their index, with rounded boxes
+    /// as `n1("…")`. Edges are `-->`, `---` without arrow heads and `<-->`
+    /// with two.
+    pub fn to_mermaid(&self) -> String {
+        let mut mermaid = String::new();
+        let _ = self.write_mermaid(&mut mermaid);
+        mermaid
+    }
+
+    fn write_mermaid<W: Write>(&self, w: &mut W) -> fmt::Result {
+        w.write_str("flowchart TD\n")?;
+        for (id, node) in self.nodes.iter().enumerate() {
+            let (open, close) = if node.region.is_rounded() {
+                ("(", ")")
+            } else {
+                ("[", "]")
+            };
+            writeln!(w, "  n{}{}{}{}", id, open, Mermaid(&node.label), close)?;
+        }
+        for edge in &self.edges {
+            let arrow = match edge.direction {
+                Direction::None => "---",
+                Direction::Forward => "-->",
+                Direction::Both => "<-->",
+            };
+            writeln!(w, "  n{} {} n{}", edge.from, arrow, edge.to)?;
+        }
+        Ok(())
+    }
+}
+
+/// A JSON string literal
+struct Json<'a>(&'a str);
+
+impl fmt::Display for Json<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_char('"')?;
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                '\r' => f.write_str("\\r")?,
+                '\t' => f.write_str("\\t")?,
+                c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+                c => f.write_char(c)?,
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+/// A DOT string literal; newlines become centered line breaks
+struct Dot<'a>(&'a str);
+
+impl fmt::Display for Dot<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_char('"')?;
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                c => f.write_char(c)?,
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+/// A quoted Mermaid label, using entity codes for characters Mermaid would
+/// otherwise parse; newlines become `<br>`
+struct Mermaid<'a>(&'a str);
+
+impl fmt::Display for Mermaid<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_char('"')?;
+        if self.0.is_empty() {
+            // Mermaid rejects empty quoted labels
+            f.write_char(' ')?;
+        }
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("#quot;")?,
+                '#' => f.write_str("#35;")?,
+                '<' => f.write_str("#lt;")?,
+                '>' => f.write_str("#gt;")?,
+                '\n' => f.write_str("<br>")?,
+                c => f.write_char(c)?,
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+/// Tolerance in cells for distances between line ends and boxes or arrows
+const EPSILON: f64 = 1e-9;
+
+/// Where a line ends at a box
+struct Attachment {
+    node: usize,
+    arrow: bool,
+}
+
+/// Build the graph of boxes and connecting lines in `diagram`.
+///
+/// Lines attach to a box where they end on its sides, or where they end
+/// outside it within one cell of them, as in `|A|-->|B|`. An arrow head
+/// within a cell of that end makes the line point at the box.
+pub(crate) fn build_graph(diagram: &Diagram) -> Graph {
+    let planar = Planar::new(&diagram.paths);
+    let faces = region_faces(&planar);
+
+    // Pieces that are sides of a box, and the boxes at each of their ends
+    let mut sides = vec![false; planar.pieces.len()];
+    let mut boxes_at: HashMap<usize, usize> = HashMap::new();
+    for (node, (_, around)) in faces.iter().enumerate() {
+        for &i in around {
+            sides[i] = true;
+            let piece = &planar.pieces[i];
+            boxes_at.entry(piece.from).or_insert(node);
+            boxes_at.entry(piece.to).or_insert(node);
+        }
+    }
+    let mut nodes: Vec<Node> = faces
+        .into_iter()
+        .map(|(region, _)| Node {
+            label: String::new(),
+            region,
+        })
+        .collect();
+    label_nodes(&mut nodes, &diagram.text);
+
+    // Group the other pieces into connectors that meet away from boxes
+    let mut connectors = Connectors::new(planar.pieces.len());
+    let mut first_at: HashMap<usize, usize> = HashMap::new();
+    let mut degree = vec![0; planar.vertices.len()];
+    for (i, piece) in planar.pieces.iter().enumerate() {
+        if sides[i] {
+            continue;
+        }
+        for v in [piece.from, piece.to] {
+            degree[v] += 1;
+            if boxes_at.contains_key(&v) {
+                continue;
+            }
+            match first_at.get(&v) {
+                Some(&other) => connectors.union(i, other),
+                None => {
+                    first_at.insert(v, i);
+                }
+            }
+        }
+    }
+
+    let regions: Vec<&Region> = nodes.iter().map(|node| &node.region).collect();
+    let arrows: Vec<Vec2> = diagram
+        .decorations
+        .iter()
+        .filter(|d| d.kind == DecorationType::Arrow)
+        .map(|d| d.pos)
+        .collect();
+    let mut attachments: Vec<Vec<Attachment>> =
+        (0..planar.pieces.len()).map(|_| Vec::new()).collect();
+    for (i, piece) in planar.pieces.iter().enumerate() {
+        if sides[i] {
+            continue;
+        }
+        for v in [piece.from, piece.to] {
+            let p = planar.vertices[v];
+            let node = match boxes_at.get(&v) {
+                Some(&node) => Some(node),
+                None if degree[v] == 1 => nearest_box(p, &regions),
+                None => None,
+            };
+            if let Some(node) = node {
+                let arrow = arrows.iter().any(|&a| cells_between(a, p) <= 1.0 + EPSILON);
+                attachments[connectors.find(i)].push(Attachment { node, arrow });
+            }
+        }
+    }
+
+    let mut edges = Vec::new();
+    for ends in &attachments {
+        let (heads, tails): (Vec<_>, Vec<_>) = ends.iter().partition(|end| end.arrow);
+        if heads.is_empty() || tails.is_empty() {
+            let direction = if heads.is_empty() {
+                Direction::None
+            } else {
+                Direction::Both
+            };
+            for (i, a) in ends.iter().enumerate() {
+                for b in &ends[i + 1..] {
+                    edges.push(Edge {
+                        from: a.node,
+                        to: b.node,
+                        direction,
+                    });
+                }
+            }
+        } else {
+            for tail in &tails {
+                for head in &heads {
+                    edges.push(Edge {
+                        from: tail.node,
+                        to: head.node,
+                        direction: Direction::Forward,
+                    });
+                }
+            }
+        }
+    }
+
+    edges.sort_by_key(|edge| (edge.from, edge.to));
+    Graph { nodes, edges }
+}
+
+/// Give each text run to the innermost box around its middle
+fn label_nodes(nodes: &mut [Node], text: &[TextRun]) {
+    let mut last_rows = vec![None; nodes.len()];
+    for run in text {
+        let middle =
+            Vec2::from_grid_frac(run.x as f64 + (run.len() as f64 - 1.0) / 2.0, run.y as f64);
+        let inside = nodes.iter().enumerate().filter(|(_, node)| {
+            let (min, max) = (node.region.min, node.region.max);
+            min.x < middle.x && middle.x < max.x && min.y < middle.y && middle.y < max.y
+        });
+        let Some((i, _)) =
+            inside.min_by(|(_, a), (_, b)| area(&a.region).total_cmp(&area(&b.region)))
+        else {
+            continue;
+        };
+        let label = &mut nodes[i].label;
+        match last_rows[i] {
+            Some(row) if row == run.y => label.push(' '),
+            Some(_) => label.push('\n'),
+            None => {}
+        }
+        label.push_str(&run.text);
+        last_rows[i] = Some(run.y);
+    }
+}
+
+fn area(region: &Region) -> f64 {
+    (region.max.x - region.min.x) * (region.max.y - region.min.y)
+}
+
+/// Distance between two points, measured in cells
+fn cells_between(a: Vec2, b: Vec2) -> f64 {
+    let dx = (a.x - b.x) / SCALE;
+    let dy = (a.y - b.y) / (SCALE * ASPECT);
+    dx.hypot(dy)
+}
+
+/// The box a loose line end at `p` leads to: the closest one within a cell,
+/// if `p` is not inside it
+fn nearest_box(p: Vec2, regions: &[&Region]) -> Option<usize> {
+    regions
+        .iter()
+        .enumerate()
+        .filter(|(_, region)| {
+            let (min, max) = (region.min, region.max);
+            !(min.x < p.x && p.x < max.x && min.y < p.y && p.y < max.y)
+        })
+        .filter_map(|(i, region)| {
+            // Round ends bulge out past the sides
+            let (min, max) = region.extent();
+            let nearest = Vec2::new(p.x.clamp(min.x, max.x), p.y.clamp(min.y, max.y));
+            let gap = cells_between(p, nearest);
+            (gap <= 1.0 + EPSILON).then_some((i, gap))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(i, _)| i)
+}
+
+/// Union-find over pieces
+struct Connectors {
+    parents: Vec<usize>,
+}
+
+impl Connectors {
+    fn new(len: usize) -> Self {
+        Self {
+            parents: (0..len).collect(),
+        }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parents[i] != i {
+            self.parents[i] = self.parents[self.parents[i]];
+            i = self.parents[i];
+        }
+        i
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        // The lower index wins, so connectors keep the order of their paths
+        self.parents[a.max(b)] = a.min(b);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::svg::RenderOptions;
+
+    fn graph(input: &str) -> Graph {
+        build_graph(&Diagram::parse(input, &RenderOptions::new()))
+    }
+
+    /// Edges as label pairs
+    fn connections(graph: &Graph) -> Vec<(&str, &str, Direction)> {
+        graph
+            .edges
+            .iter()
+            .map(|e| {
+                let label = |i: usize| graph.nodes[i].label.as_str();
+                (label(e.from), label(e.to), e.direction)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_labels() {
+        let g = graph("+-------+\n| Auth  |\n| proxy |\n+-------+   loose");
+        assert_eq!(g.nodes.len(), 1);
+        assert_eq!(g.nodes[0].label, "Auth\nproxy");
+
+        let g = graph("+--------------+\n| outer +---+  |\n|       | X |  |\n|       +---+  |\n+--------------+");
+        let labels: Vec<_> = g.nodes.iter().map(|n| n.label.as_str()).collect();
+        assert_eq!(labels, vec!["outer", "X"]);
+    }
+
+    #[test]
+    fn test_arrow_directions() {
+        let g = graph("+-+   +-+  +-+\n|A|-->|B|<-+C|\n+-+   +-+  +-+\n |\n v\n+-+\n|D|\n+-+");
+        assert_eq!(
+            connections(&g),
+            vec![
+                ("A", "B", Direction::Forward),
+                ("A", "D", Direction::Forward),
+                ("C", "B", Direction::Forward),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plain_and_double_headed_lines() {
+        let g = graph("+-+    +-+    +-+\n|A|----|B|<-->|C|\n+-+    +-+    +-+");
+        assert_eq!(
+            connections(&g),
+            vec![("A", "B", Direction::None), ("B", "C", Direction::Both)]
+        );
+    }
+
+    #[test]
+    fn test_branching_and_bent_lines() {
+        let input = "\
++---+         +---+
+| S |----+--->| X |
++---+    |    +---+
+         |    +---+
+         +--->| Y |
+              +---+";
+        assert_eq!(
+            connections(&graph(input)),
+            vec![
+                ("S", "X", Direction::Forward),
+                ("S", "Y", Direction::Forward)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_round_ends_and_corners() {
+        let g = graph("  .---.\n (  c  )-->+-+\n  '---'    |B|\n           +-+");
+        assert_eq!(connections(&g), vec![("c", "B", Direction::Forward)]);
+    }
+
+    #[test]
+    fn test_unattached_lines() {
+        // Too far from the box, and inside it
+        let g = graph("+---+\n| - |   -->\n+---+");
+        assert!(g.edges.is_empty());
+    }
+
+    #[test]
+    fn test_json() {
+        let g = graph("+-+   +-+\n|\"|-->|B|\n+-+   +-+");
+        assert_eq!(
+            g.to_json(),
+            r#"{
+  "nodes": [
+    {"id": 0, "label": "\"", "x": 0, "y": 0, "width": 2, "height": 2, "rounded": false},
+    {"id": 1, "label": "B", "x": 6, "y": 0, "width": 2, "height": 2, "rounded": false}
+  ],
+  "edges": [
+    {"from": 0, "to": 1, "direction": "forward"}
+  ]
+}
+"#
+        );
+        assert_eq!(
+            Graph::default().to_json(),
+            "{\n  \"nodes\": [],\n  \"edges\": []\n}\n"
+        );
+        assert_eq!(Json("a\\b\tc\u{1}").to_string(), r#""a\\b\tc\u0001""#);
+    }
+
+    const CONNECTED: &str = "\
++-----+     .---.     +-+
+|x \"y\"|--->(  z  )<-->| |
+|  #2 |     '---'     +-+
++-----+       |
+              |
+             +-+
+             |d|
+             +-+";
+
+    #[test]
+    fn test_dot() {
+        assert_eq!(
+            graph(CONNECTED).to_dot(),
+            r#"digraph {
+  node [shape=box];
+  n0 [label="x \"y\"\n#2"];
+  n1 [label="z", style=rounded];
+  n2 [label=""];
+  n3 [label="d"];
+  n0 -> n1;
+  n1 -> n2 [dir=both];
+  n1 -> n3 [dir=none];
+}
+"#
+        );
+        assert_eq!(Dot("a\\b").to_string(), r#""a\\b""#);
+    }
+
+    #[test]
+    fn test_mermaid() {
+        assert_eq!(
+            graph(CONNECTED).to_mermaid(),
+            r##"flowchart TD
+  n0["x #quot;y#quot;<br>#35;2"]
+  n1("z")
+  n2[" "]
+  n3["d"]
+  n0 --> n1
+  n1 <--> n2
+  n1 --- n3
+"##
+        );
+        assert_eq!(Mermaid("<b>").to_string(), r##""#lt;b#gt;""##);
+    }
+}
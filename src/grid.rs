@@ -10,10 +10,10 @@ use crate::chars::*;
 
 /// 2D grid of characters with "used" tracking
 pub struct Grid {
-    /// Characters in the grid (row-major order)
-    chars: Vec<Vec<char>>,
-    /// Track which cells have been consumed
-    used: Vec<Vec<bool>>,
+    /// Characters in the grid, row-major, `width * height` cells
+    chars: Vec<char>,
+    /// One bit per cell, set once the cell has been consumed
+    used: Vec<u64>,
     /// Grid width (longest line)
     pub width: usize,
     /// Grid height (number of lines)
@@ -22,71 +22,104 @@ pub struct Grid {
 
 impl Grid {
     /// Create a grid from a diagram string
+    ///
+    /// Preprocessing happens while the cells are filled in, in one pass over
+    /// the input:
+    /// - Remove common leading whitespace
+    /// - Pad all lines to the same length with spaces
+    /// - Hide marker characters in text (o, v, V)
     pub fn new(input: &str) -> Self {
-        let input = preprocess(input);
         let lines: Vec<&str> = input.lines().collect();
+        let indent = common_indent(&lines);
+
+        let width = lines
+            .iter()
+            .map(|l| l.chars().count().saturating_sub(indent))
+            .max()
+            .unwrap_or(0);
+        let mut height = lines.len();
+        if width == 0 {
+            // Nothing but empty lines
+            height = 0;
+        } else if indent > 0 && lines[height - 1].chars().count() <= indent {
+            // A last line holding only (part of) the indent does not count,
+            // so an indented block may end with its closing indentation
+            height -= 1;
+        }
 
-        let height = lines.len();
-        let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
-
-        let mut chars = Vec::with_capacity(height);
-        let mut used = Vec::with_capacity(height);
-
-        for line in &lines {
-            let mut row: Vec<char> = line.chars().collect();
-            // Pad to width
-            while row.len() < width {
-                row.push(' ');
+        let mut chars = Vec::with_capacity(width * height);
+        for line in &lines[..height] {
+            let row_start = chars.len();
+            let mut row = line.chars().skip(indent).peekable();
+            let mut left = ' ';
+            while let Some(c) = row.next() {
+                let right = row.peek().copied().unwrap_or(' ');
+                chars.push(hide_marker(c, left, right));
+                left = c;
             }
-            chars.push(row);
-            used.push(vec![false; width]);
+            chars.resize(row_start + width, ' ');
         }
 
         Self {
             chars,
-            used,
+            used: vec![0; (width * height + 63) / 64],
             width,
             height,
         }
     }
 
-    /// Get the character at position (x, y), or space if out of bounds
+    /// Index of the cell at (x, y), or None if out of bounds
     #[inline]
-    pub fn get(&self, x: i32, y: i32) -> char {
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
         if x < 0 || y < 0 {
-            return ' ';
+            return None;
         }
         let x = x as usize;
         let y = y as usize;
         if y >= self.height || x >= self.width {
-            return ' ';
+            return None;
+        }
+        Some(y * self.width + x)
+    }
+
+    /// Get the character at position (x, y), or space if out of bounds
+    #[inline]
+    pub fn get(&self, x: i32, y: i32) -> char {
+        match self.index(x, y) {
+            Some(i) => self.chars[i],
+            None => ' ',
         }
-        self.chars[y][x]
     }
 
     /// Mark a cell as used (consumed by path/decoration finding)
     pub fn set_used(&mut self, x: i32, y: i32) {
-        if x < 0 || y < 0 {
-            return;
-        }
-        let x = x as usize;
-        let y = y as usize;
-        if y < self.height && x < self.width {
-            self.used[y][x] = true;
+        if let Some(i) = self.index(x, y) {
+            self.used[i / 64] |= 1 << (i % 64);
         }
     }
 
     /// Check if a cell has been used
     pub fn is_used(&self, x: i32, y: i32) -> bool {
-        if x < 0 || y < 0 {
-            return false;
-        }
-        let x = x as usize;
-        let y = y as usize;
-        if y >= self.height || x >= self.width {
-            return false;
+        match self.index(x, y) {
+            Some(i) => self.used[i / 64] & (1 << (i % 64)) != 0,
+            None => false,
         }
-        self.used[y][x]
+    }
+
+    /// Characters of each row with markers unhidden and trailing spaces
+    /// trimmed
+    pub fn rows(&self) -> Vec<String> {
+        self.chars
+            .chunks(self.width.max(1))
+            .map(|row| unhide_markers(row.iter().collect::<String>().trim_end()))
+            .collect()
+    }
+
+    /// Used flags of all cells, row-major
+    pub fn used_cells(&self) -> Vec<bool> {
+        (0..self.width * self.height)
+            .map(|i| self.used[i / 64] & (1 << (i % 64)) != 0)
+            .collect()
     }
 
     // ========================================================================
@@ -254,117 +287,30 @@ impl Grid {
     }
 }
 
-/// Preprocess the diagram string:
-/// - Equalize line lengths (pad with spaces)
-/// - Remove common leading whitespace
-/// - Hide marker characters in text (o, v, V)
-fn preprocess(input: &str) -> String {
-    let input = remove_leading_space(input);
-    let input = equalize_line_lengths(&input);
-    hide_markers(&input)
-}
-
-/// Remove common leading whitespace from all lines
-fn remove_leading_space(input: &str) -> String {
-    let lines: Vec<&str> = input.lines().collect();
-
-    // Find minimum leading spaces (ignoring empty lines)
-    let min_spaces = lines
+/// Number of leading whitespace characters common to all non-blank lines
+fn common_indent(lines: &[&str]) -> usize {
+    lines
         .iter()
         .filter(|l| !l.trim().is_empty())
-        .map(|l| l.len() - l.trim_start().len())
+        .map(|l| l.chars().take_while(|c| c.is_whitespace()).count())
         .min()
-        .unwrap_or(0);
-
-    if min_spaces == 0 {
-        return input.to_string();
-    }
-
-    lines
-        .iter()
-        .map(|l| {
-            if l.len() >= min_spaces {
-                &l[min_spaces..]
-            } else {
-                ""
-            }
-        })
-        .collect::<Vec<_>>()
-        .join("\n")
-}
-
-/// Pad all lines to the same length
-fn equalize_line_lengths(input: &str) -> String {
-    let lines: Vec<&str> = input.lines().collect();
-    let max_len = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
-
-    lines
-        .iter()
-        .map(|l| {
-            let len = l.chars().count();
-            if len < max_len {
-                let padding = " ".repeat(max_len - len);
-                format!("{}{}", l, padding)
-            } else {
-                l.to_string()
-            }
-        })
-        .collect::<Vec<_>>()
-        .join("\n")
+        .unwrap_or(0)
 }
 
 /// Hide 'o', 'v', 'V' characters that appear to be part of text
-/// (surrounded by letters, not connected to lines)
-fn hide_markers(input: &str) -> String {
-    let lines: Vec<Vec<char>> = input.lines().map(|l| l.chars().collect()).collect();
-    let height = lines.len();
-
-    let get = |x: i32, y: i32| -> char {
-        if y < 0 || y >= height as i32 {
-            return ' ';
-        }
-        let row = &lines[y as usize];
-        if x < 0 || x >= row.len() as i32 {
-            return ' ';
-        }
-        row[x as usize]
-    };
-
-    let is_letter = |c: char| -> bool { c.is_ascii_alphabetic() };
-
-    let mut result: Vec<Vec<char>> = lines.clone();
-
-    for y in 0..height {
-        for x in 0..lines[y].len() {
-            let c = lines[y][x];
-            let xi = x as i32;
-            let yi = y as i32;
-
-            // Check if o, v, V is part of a word (surrounded by letters)
-            if c == 'o' || c == 'v' || c == 'V' {
-                let left = get(xi - 1, yi);
-                let right = get(xi + 1, yi);
-
-                // If surrounded by letters on left or right, it's part of text
-                if is_letter(left) || is_letter(right) {
-                    // Replace with a placeholder that won't be detected as decoration
-                    // Use a private use Unicode character
-                    result[y][x] = match c {
-                        'o' => '\u{E000}', // Private use for 'o'
-                        'v' => '\u{E001}', // Private use for 'v'
-                        'V' => '\u{E002}', // Private use for 'V'
-                        _ => c,
-                    };
-                }
-            }
-        }
+/// (next to a letter, so not connected to lines)
+fn hide_marker(c: char, left: char, right: char) -> char {
+    if !left.is_ascii_alphabetic() && !right.is_ascii_alphabetic() {
+        return c;
+    }
+    // Replace with a private use character that won't be detected as a
+    // decoration
+    match c {
+        'o' => '\u{E000}',
+        'v' => '\u{E001}',
+        'V' => '\u{E002}',
+        _ => c,
     }
-
-    result
-        .iter()
-        .map(|row| row.iter().collect::<String>())
-        .collect::<Vec<_>>()
-        .join("\n")
 }
 
 /// Unhide previously hidden marker characters
@@ -410,16 +356,41 @@ mod tests {
 
     #[test]
     fn test_remove_leading_space() {
-        let input = "  abc\n  def";
-        let result = remove_leading_space(input);
-        assert_eq!(result, "abc\ndef");
+        let grid = Grid::new("  abc\n  def\n\n   gh\n  ");
+        assert_eq!(grid.width, 3);
+        assert_eq!(grid.height, 4);
+        assert_eq!(grid.get(0, 0), 'a');
+        assert_eq!(grid.get(0, 1), 'd');
+        assert_eq!(grid.get(1, 3), 'g');
     }
 
     #[test]
     fn test_equalize_line_lengths() {
-        let input = "ab\na";
-        let result = equalize_line_lengths(input);
-        assert_eq!(result, "ab\na ");
+        let grid = Grid::new("ab\na");
+        assert_eq!(grid.width, 2);
+        assert_eq!(grid.get(1, 1), ' ');
+    }
+
+    #[test]
+    fn test_hide_markers() {
+        let grid = Grid::new("vo o-v\nVideo");
+        assert_eq!(grid.get(0, 0), '\u{E001}');
+        assert_eq!(grid.get(1, 0), '\u{E000}');
+        assert_eq!(grid.get(3, 0), 'o');
+        assert_eq!(grid.get(5, 0), 'v');
+        assert_eq!(grid.get(0, 1), '\u{E002}');
+        assert_eq!(grid.get(4, 1), '\u{E000}');
+    }
+
+    #[test]
+    fn test_used_bits_span_words() {
+        let mut grid = Grid::new(&"x".repeat(100));
+        grid.set_used(63, 0);
+        grid.set_used(64, 0);
+        assert!(grid.is_used(63, 0));
+        assert!(grid.is_used(64, 0));
+        assert!(!grid.is_used(62, 0));
+        assert!(!grid.is_used(65, 0));
     }
 
     #[test]
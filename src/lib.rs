@@ -20,34 +20,198 @@
 //! println!("{}", svg);
 //! ```
 //!
+//! ## Inspecting Diagrams
+//!
+//! [`parse`] runs the same detection as [`render`] but returns a [`Diagram`]
+//! with the detected paths, decorations and text runs, so they can be
+//! examined or modified before rendering.
+//!
+//! ```rust
+//! use aasvg::{parse, DecorationType, RenderOptions};
+//!
+//! let diagram = parse("+--+\n|  |--->\n+--+");
+//! let arrows = diagram
+//!     .decorations
+//!     .iter()
+//!     .filter(|d| d.kind == DecorationType::Arrow)
+//!     .count();
+//! assert_eq!(arrows, 1);
+//!
+//! let svg = diagram.to_svg(&RenderOptions::default());
+//! assert!(svg.contains("<polygon"));
+//! ```
+//!
+//! When a line does not come out as expected,
+//! [`RenderOptions::with_show_grid`] overlays the character cells and the
+//! ends of every path, and [`RenderOptions::with_show_used`] tints the cells
+//! the finders consumed.
+//!
 //! ## Supported Elements
 //!
 //! - **Lines**: `-`, `|`, `/`, `\`, `=`, `~`, `_`
+//! - **Dashed lines**: `┄`, `┈`, `╌` (horizontal), `┆`, `┊`, `╎`, `:` (vertical)
+//! - **Box drawing**: the lines, corners, tees and crosses of the U+2500
+//!   block, e.g. `─`, `│`, `┌`, `┼`, `═`, `╬`, `━`, `┃`, `╒`; rounded corners
+//!   `╭`, `╮`, `╯`, `╰` are drawn as curves. The diagonals `╱`, `╲` and `╳`
+//!   are not supported
+//! - **Thick lines**: heavy box-drawing characters (`━`, `┃`, `┏`, …), drawn
+//!   with the `--aasvg-thick-width` stroke width
 //! - **Vertices**: `+`, `.`, `'`, `,`, `` ` ``
 //! - **Arrows**: `>`, `<`, `^`, `v`, `V`
 //! - **Points**: `o`, `*`, `●`, `○`, `◍`, `◌`, `⊕`
 //! - **Jumps**: `(`, `)` for line crossings
+//! - **Gray fills**: `▁`, `▂`, `▃`, `█`, drawn with `--aasvg-gray-1` to
+//!   `--aasvg-gray-4` so they invert in dark mode
 //! - **Text**: Any other characters are rendered as text
 //!
 //! ## Light/Dark Mode
 //!
 //! The generated SVG includes CSS that uses `prefers-color-scheme` to
 //! automatically switch colors based on the user's system preference.
+//! The colors come from a [`Theme`]; besides the default there are
+//! [`Theme::github`] and [`Theme::high_contrast`] presets, or build your own
+//! from two [`Palette`]s and pass it with [`RenderOptions::with_theme`].
+//!
+//! By default the CSS variables are set on `:root`. When inlining several
+//! diagrams into one HTML page, use [`RenderOptions::with_css_scope`] to
+//! scope them to each SVG ([`CssScope::Unique`] or [`CssScope::Id`]) or to
+//! `svg.diagram` ([`CssScope::Diagram`]).
+//!
+//! For consumers that ignore `<style>` and `var()` (xml2rfc, email clients,
+//! some PDF converters), [`ColorMode::Inline`] writes the literal colors of
+//! the light or dark palette ([`Scheme`]) into the elements instead. [`ColorMode::Light`] and [`ColorMode::Dark`] keep
+//! the CSS variables but pin them to one palette.
+//!
+//! ## Accessibility
+//!
+//! [`RenderOptions::with_title`] and [`RenderOptions::with_description`] add
+//! `<title>` and `<desc>` elements, and mark the SVG with `role="img"` and
+//! `aria-labelledby` so screen readers announce them. [`Description::Auto`]
+//! describes the diagram with its own text.
+//!
+//! ```rust
+//! use aasvg::{render_with_options, Description, RenderOptions};
+//!
+//! let options = RenderOptions::new()
+//!     .with_title("Request flow")
+//!     .with_description(Description::Auto);
+//! let svg = render_with_options("+--------+\n| client |-->  server\n+--------+", &options);
+//! assert!(svg.contains(r#"role="img""#));
+//! assert!(svg.contains(">client, server</desc>"));
+//! ```
+//!
+//! ## Sizing
+//!
+//! The SVG is sized to the character grid. [`RenderOptions::with_fill_container`]
+//! leaves out `width` and `height` so it scales to its container instead,
+//! [`RenderOptions::with_size`] fixes the viewport to a number of characters,
+//! and [`RenderOptions::with_margin`] pads or trims it by some pixels.
+//! [`RenderOptions::with_attribute`] sets any other attribute of the root
+//! `<svg>`, such as a class or a font stack, replacing the default of the
+//! same name.
+//!
+//! Lines and text are laid out on 8×16 pixel cells with 13px text. To match
+//! a font with other proportions, set [`RenderOptions::with_cell_width`] to
+//! its advance, [`RenderOptions::with_aspect`] to its line height over that,
+//! and [`RenderOptions::with_font_size`]; every output format follows them.
+//!
+//! ```rust
+//! use aasvg::{render_with_options, RenderOptions};
+//!
+//! // A monospace font 0.6em wide at 16px, with a line height of 1.2
+//! let options = RenderOptions::new().with_cell_width(9.6).with_font_size(16.0);
+//! let svg = render_with_options("+--+\n|Hi|\n+--+", &options);
+//! assert!(svg.contains(r#"width="48" height="76.8""#));
+//! assert!(svg.contains(r#"font-size="16px""#));
+//! ```
+//!
+//! ## Box Fills
+//!
+//! [`Diagram::regions`] lists the closed boxes formed by the lines, including
+//! ones with rounded corners. With [`RenderOptions::with_fill_boxes`] they are
+//! filled with the `--aasvg-box-fill` color, drawn under the lines.
+//!
+//! ```rust
+//! use aasvg::{parse, render_with_options, RenderOptions};
+//!
+//! let diagram = "+--+  .--.\n|  |--|  |\n+--+  '--'";
+//! assert_eq!(parse(diagram).regions.len(), 2);
+//!
+//! let svg = render_with_options(diagram, &RenderOptions::new().with_fill_boxes(true));
+//! assert!(svg.contains(r#"<g fill="var(--aasvg-box-fill)">"#));
+//! ```
+//!
+//! ## Semantic Graph
+//!
+//! [`Diagram::to_graph`] describes what a diagram connects: each box becomes
+//! a [`Node`] labelled with the text inside it, and each chain of lines
+//! between two boxes an [`Edge`], directed by its arrow heads.
+//! [`Graph::to_json`] serializes it for other tools, while [`Graph::to_dot`]
+//! and [`Graph::to_mermaid`] convert it to a Graphviz graph or a Mermaid
+//! flowchart to be laid out automatically.
+//!
+//! ```rust
+//! use aasvg::{parse, Direction};
+//!
+//! let graph = parse("+-----+    +----+\n| web |--->| db |\n+-----+    +----+").to_graph();
+//! let edge = graph.edges[0];
+//! assert_eq!(graph.nodes[edge.from].label, "web");
+//! assert_eq!(graph.nodes[edge.to].label, "db");
+//! assert_eq!(edge.direction, Direction::Forward);
+//! assert!(graph.to_json().contains(r#""label": "web""#));
+//! assert!(graph.to_mermaid().contains("n0 --> n1"));
+//! ```
+//!
+//! ## Streaming Output
+//!
+//! [`render_to`] and [`render_to_io`] write the SVG straight into any
+//! [`fmt::Write`] or [`io::Write`], skipping the intermediate `String`.
+//!
+//! ## PNG Output
+//!
+//! With the `png` feature, [`render_png`] rasterizes diagrams with
+//! [resvg](https://docs.rs/resvg) and a bundled copy of the Hack font.
+//!
+//! ## PDF Output
+//!
+//! With the `pdf` feature, [`render_pdf`] draws the same geometry directly
+//! as a vector PDF page, embedding the glyphs it uses from the Hack font.
+//!
+//! ## TikZ Output
+//!
+//! [`render_tikz`] turns a diagram into a `tikzpicture` for LaTeX documents,
+//! with text set in the document's font. It needs the `tikz` LaTeX package.
+
+use std::{fmt, io};
 
 mod chars;
 mod decoration;
+mod diagram;
 mod finder;
+mod graph;
 mod grid;
 mod path;
+#[cfg(feature = "pdf")]
+mod pdf;
+#[cfg(feature = "png")]
+mod png;
+mod region;
+mod shape;
 mod svg;
+mod theme;
+mod tikz;
 
-pub use svg::RenderOptions;
-
-use decoration::DecorationSet;
-use finder::{find_decorations, find_paths};
-use grid::Grid;
-use path::PathSet;
-use svg::generate_svg;
+pub use decoration::{Decoration, DecorationSet, DecorationType};
+pub use diagram::{Diagram, TextRun};
+pub use graph::{Direction, Edge, Graph, Node};
+pub use path::{Path, PathSet, PathStyle, Vec2, ASPECT, SCALE};
+#[cfg(feature = "png")]
+pub use png::PngError;
+pub use region::Region;
+pub use svg::{
+    is_attribute_name, ColorMode, CssScope, Description, RenderOptions, Scheme, SourceOverlay,
+};
+pub use theme::{Palette, Theme};
 
 /// Render an ASCII art diagram to SVG.
 ///
@@ -82,14 +246,144 @@ pub fn render(input: &str) -> String {
 /// assert!(svg.contains("var(--aasvg-bg)"));
 /// ```
 pub fn render_with_options(input: &str, options: &RenderOptions) -> String {
-    let mut grid = Grid::new(input);
-    let mut paths = PathSet::new();
-    let mut decorations = DecorationSet::new();
+    parse_with_options(input, options).to_svg(options)
+}
+
+/// Render an ASCII art diagram to SVG, writing it straight into `w`.
+///
+/// Produces the same output as [`render_with_options`] without building
+/// the document in an intermediate `String`, which helps when rendering
+/// many diagrams into one buffer.
+///
+/// # Example
+///
+/// ```rust
+/// use aasvg::{render_to, RenderOptions};
+///
+/// let mut page = String::from("<figure>");
+/// render_to("+--+\n|  |\n+--+", &RenderOptions::new(), &mut page).unwrap();
+/// page.push_str("</figure>");
+/// assert!(page.contains("<figure><svg"));
+/// ```
+pub fn render_to<W: fmt::Write>(input: &str, options: &RenderOptions, w: &mut W) -> fmt::Result {
+    parse_with_options(input, options).write_svg(options, w)
+}
+
+/// Render an ASCII art diagram to SVG, writing it into an [`io::Write`].
+///
+/// The output is written in many small pieces, so wrap unbuffered writers
+/// such as files or sockets in a [`std::io::BufWriter`].
+///
+/// # Example
+///
+/// ```rust
+/// use aasvg::{render_to_io, RenderOptions};
+///
+/// let mut bytes = Vec::new();
+/// render_to_io("-->", &RenderOptions::new(), &mut bytes).unwrap();
+/// assert!(bytes.starts_with(b"<svg"));
+/// ```
+pub fn render_to_io<W: io::Write>(
+    input: &str,
+    options: &RenderOptions,
+    w: &mut W,
+) -> io::Result<()> {
+    parse_with_options(input, options).write_svg_io(options, w)
+}
 
-    find_paths(&mut grid, &mut paths);
-    find_decorations(&mut grid, &paths, &mut decorations);
+/// Render an ASCII art diagram to PNG bytes.
+///
+/// Requires the `png` feature. Text is drawn with a bundled monospace font.
+/// The light palette of `options.theme` is used unless `options.color_mode`
+/// is [`ColorMode::Dark`]; `scale` multiplies the image size.
+///
+/// # Errors
+///
+/// Returns a [`PngError`] if `scale` is not positive, the image would be
+/// empty or too large, or resvg rejects the SVG.
+///
+/// # Example
+///
+/// ```rust
+/// use aasvg::{render_png, ColorMode, RenderOptions};
+///
+/// let options = RenderOptions::new()
+///     .with_backdrop(true)
+///     .with_color_mode(ColorMode::Dark);
+///
+/// let png = render_png("+--+\n|  |\n+--+", &options, 2.0).unwrap();
+/// assert!(png.starts_with(b"\x89PNG"));
+/// assert!(render_png("+--+", &options, 0.0).is_err());
+/// ```
+#[cfg(feature = "png")]
+pub fn render_png(input: &str, options: &RenderOptions, scale: f32) -> Result<Vec<u8>, PngError> {
+    parse_with_options(input, options).to_png(options, scale)
+}
 
-    generate_svg(&mut grid, &paths, &decorations, options)
+/// Render an ASCII art diagram to a one-page vector PDF.
+///
+/// Requires the `pdf` feature. The page is sized from the diagram grid,
+/// and text is set in an embedded subset of a bundled monospace font. The
+/// light palette of `options.theme` is used unless `options.color_mode` is
+/// [`ColorMode::Dark`].
+///
+/// # Example
+///
+/// ```rust
+/// use aasvg::{render_pdf, RenderOptions};
+///
+/// let pdf = render_pdf("+--+\n|Hi|\n+--+", &RenderOptions::new());
+/// assert!(pdf.starts_with(b"%PDF-"));
+/// ```
+#[cfg(feature = "pdf")]
+pub fn render_pdf(input: &str, options: &RenderOptions) -> Vec<u8> {
+    parse_with_options(input, options).to_pdf(options)
+}
+
+/// Render an ASCII art diagram to a TikZ `tikzpicture` environment.
+///
+/// Text becomes nodes set in the document's font. Colors are declared with
+/// `\providecolor` as `aasvg-stroke`, `aasvg-fill`, `aasvg-bg`, `aasvg-text`,
+/// `aasvg-gray-1` to `aasvg-gray-4`, `aasvg-shade` and `aasvg-box-fill`;
+/// define them before the picture to override the theme. `stretch` is ignored.
+///
+/// # Example
+///
+/// ```rust
+/// use aasvg::{render_tikz, RenderOptions};
+///
+/// let tikz = render_tikz("+--+\n|Hi|\n+--+", &RenderOptions::new());
+/// assert!(tikz.starts_with("\\begin{tikzpicture}"));
+/// assert!(tikz.contains("{Hi};"));
+/// ```
+pub fn render_tikz(input: &str, options: &RenderOptions) -> String {
+    parse_with_options(input, options).to_tikz(options)
+}
+
+/// Parse an ASCII art diagram without rendering it.
+///
+/// The returned [`Diagram`] exposes the detected paths, decorations and
+/// text runs. Render it with [`Diagram::to_svg`].
+///
+/// # Example
+///
+/// ```rust
+/// use aasvg::parse;
+///
+/// let diagram = parse("-->  Label");
+/// assert_eq!(diagram.paths.len(), 1);
+/// assert_eq!(diagram.text[0].text, "Label");
+/// ```
+pub fn parse(input: &str) -> Diagram {
+    parse_with_options(input, &RenderOptions::default())
+}
+
+/// Parse an ASCII art diagram with custom options.
+///
+/// Only [`RenderOptions::spaces`] affects parsing; the other options are
+/// applied when the diagram is rendered.
+pub fn parse_with_options(input: &str, options: &RenderOptions) -> Diagram {
+    Diagram::parse(input, options)
 }
 
 #[cfg(test)]
@@ -139,6 +433,48 @@ mod tests {
         assert!(svg.contains("Hello"));
     }
 
+    #[test]
+    fn test_parse_matches_render() {
+        let input = "+--+\n|Hi|--->\n+--+";
+        let options = RenderOptions::new();
+        let diagram = parse_with_options(input, &options);
+        assert_eq!(
+            diagram.to_svg(&options),
+            render_with_options(input, &options)
+        );
+    }
+
+    #[test]
+    fn test_render_to_matches_render() {
+        let input = "+--+\n|<>|--->\n+--+\n  o  ▂";
+        let options = RenderOptions::new().with_stretch(true);
+        let expected = render_with_options(input, &options);
+
+        let mut svg = String::new();
+        render_to(input, &options, &mut svg).unwrap();
+        assert_eq!(svg, expected);
+
+        let mut bytes = Vec::new();
+        render_to_io(input, &options, &mut bytes).unwrap();
+        assert_eq!(bytes, expected.as_bytes());
+    }
+
+    #[test]
+    fn test_render_to_io_error() {
+        struct Full;
+        impl io::Write for Full {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::WriteZero, "full"))
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let err = render_to_io("-->", &RenderOptions::new(), &mut Full).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WriteZero);
+    }
+
     #[test]
     fn test_disable_text() {
         let options = RenderOptions::new().with_disable_text(true);
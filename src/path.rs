@@ -6,14 +6,24 @@
 // Many methods are provided for library consumers but not used internally
 #![allow(dead_code)]
 
-use std::fmt::Write;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{self, Write};
+
+use crate::shape::{Paint, Segment, Shape};
+use crate::theme::Colors;
 
 /// Scaling factor: pixels per character cell
+///
+/// Parsed geometry is always laid out on cells of this size; renderers
+/// rescale it to [`crate::RenderOptions::cell_width`].
 pub const SCALE: f64 = 8.0;
 
 /// Aspect ratio for Y axis (characters are taller than wide)
 pub const ASPECT: f64 = 2.0;
 
+/// Font size in pixels that fits cells of [`SCALE`] by [`ASPECT`]
+pub(crate) const FONT_SIZE: f64 = 13.0;
+
 /// Bezier curve constant for smooth circular arcs
 /// This is the "magic number" 4*(sqrt(2)-1)/3 for quarter-circle approximation
 pub const CURVE: f64 = 0.551915;
@@ -23,6 +33,100 @@ pub fn diagonal_angle() -> f64 {
     (ASPECT).atan().to_degrees()
 }
 
+/// Size of the character cells and text of a rendered diagram.
+///
+/// Maps the parsed geometry, laid out on cells of [`SCALE`] by
+/// [`ASPECT`], to cells of any size. With the defaults every point maps to
+/// itself exactly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Metrics {
+    /// Cell width in pixels
+    pub(crate) cell_width: f64,
+    /// Cell height in pixels
+    pub(crate) cell_height: f64,
+    /// Font size in pixels
+    pub(crate) font_size: f64,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new(SCALE, ASPECT, FONT_SIZE)
+    }
+}
+
+impl Metrics {
+    pub(crate) fn new(cell_width: f64, aspect: f64, font_size: f64) -> Self {
+        Self {
+            cell_width,
+            cell_height: cell_width * aspect,
+            font_size,
+        }
+    }
+
+    /// Position of a parsed point on these cells
+    pub(crate) fn point(&self, p: Vec2) -> Vec2 {
+        Vec2::new(
+            p.x / SCALE * self.cell_width,
+            p.y / (SCALE * ASPECT) * self.cell_height,
+        )
+    }
+
+    /// `segment` with its points moved onto these cells
+    pub(crate) fn segment(&self, segment: Segment) -> Segment {
+        match segment {
+            Segment::Move(p) => Segment::Move(self.point(p)),
+            Segment::Line(p) => Segment::Line(self.point(p)),
+            Segment::Quad(c, p) => Segment::Quad(self.point(c), self.point(p)),
+            Segment::Cubic(c, d, p) => Segment::Cubic(self.point(c), self.point(d), self.point(p)),
+            Segment::Close => Segment::Close,
+        }
+    }
+
+    /// `outline` with its points moved onto these cells
+    pub(crate) fn outline(&self, outline: Vec<Segment>) -> Vec<Segment> {
+        outline.into_iter().map(|s| self.segment(s)).collect()
+    }
+
+    /// A parsed rotation in degrees, turned to keep pointing along the same
+    /// cells when they have another aspect ratio
+    pub(crate) fn angle(&self, degrees: f64) -> f64 {
+        // Axis-aligned angles keep their exact value
+        if self.cell_height == self.cell_width * ASPECT || degrees % 90.0 == 0.0 {
+            return degrees;
+        }
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let (x, y) = (cos * self.cell_width, sin * self.cell_height / ASPECT);
+        y.atan2(x).to_degrees().rem_euclid(360.0)
+    }
+
+    /// Radius of point decorations: 2px less than the cell width, but no
+    /// less than a quarter of it on narrow cells
+    pub(crate) fn point_radius(&self) -> f64 {
+        (self.cell_width - 2.0).max(self.cell_width / 4.0)
+    }
+
+    /// A stroke width or dash length of `pixels` on the default cells,
+    /// scaled with the cell width
+    pub(crate) fn length(&self, pixels: f64) -> f64 {
+        pixels / SCALE * self.cell_width
+    }
+
+    /// Stroke width of thick lines, 3px on the default cells
+    pub(crate) fn thick_width(&self) -> f64 {
+        self.length(3.0)
+    }
+
+    /// Dash and gap lengths of dashed lines, 4px and 2px on the default cells
+    pub(crate) fn dash(&self) -> (f64, f64) {
+        (self.length(4.0), self.length(2.0))
+    }
+
+    /// Offset of the text baseline below the cell center, 4px at 13px
+    pub(crate) fn baseline(&self) -> f64 {
+        self.font_size * 4.0 / FONT_SIZE
+    }
+}
+
 /// 2D point/vector with SVG coordinate formatting
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Vec2 {
@@ -69,32 +173,170 @@ impl Vec2 {
 
     /// Format as "x,y" for SVG path data
     pub fn coords(&self) -> String {
-        format!("{},{}", format_coord(self.x), format_coord(self.y))
+        format!("{},{}", Coord(self.x), Coord(self.y))
     }
 
     /// Format as "x,y " with trailing space for SVG path data
-    pub fn to_svg(&self) -> String {
-        format!("{},{} ", format_coord(self.x), format_coord(self.y))
+    pub fn to_svg(self) -> String {
+        format!("{},{} ", Coord(self.x), Coord(self.y))
     }
 }
 
 /// Format a coordinate for SVG output, matching the JS behavior:
 /// - Use 5 decimal places max
 /// - Strip trailing zeros and decimal point
-fn format_coord(x: f64) -> String {
-    let s = format!("{:.5}", x);
-    // Strip trailing zeros and decimal point
-    let s = s.trim_end_matches('0');
-    let s = s.trim_end_matches('.');
-    s.to_string()
+pub(crate) fn format_coord(x: f64) -> String {
+    Coord(x).to_string()
 }
 
+/// A coordinate that displays like [`format_coord`] without allocating
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Coord(pub f64);
+
+impl fmt::Display for Coord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Fixed-size buffer; only absurdly large values need the heap
+        struct Buf {
+            bytes: [u8; 40],
+            len: usize,
+        }
+        impl Write for Buf {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                let end = self.len + s.len();
+                let dest = self.bytes.get_mut(self.len..end).ok_or(fmt::Error)?;
+                dest.copy_from_slice(s.as_bytes());
+                self.len = end;
+                Ok(())
+            }
+        }
+
+        let mut buf = Buf {
+            bytes: [0; 40],
+            len: 0,
+        };
+        if write!(buf, "{:.5}", self.0).is_err() {
+            let s = format!("{:.5}", self.0);
+            return f.write_str(s.trim_end_matches('0').trim_end_matches('.'));
+        }
+        // Formatted floats are ASCII
+        let s = std::str::from_utf8(&buf.bytes[..buf.len]).map_err(|_| fmt::Error)?;
+        f.write_str(s.trim_end_matches('0').trim_end_matches('.'))
+    }
+}
+
+/// Write the `d` attribute value for one outline
+pub(crate) fn write_path_data<W: Write>(
+    w: &mut W,
+    outline: &[Segment],
+    trailing_space: bool,
+) -> fmt::Result {
+    for (i, segment) in outline.iter().enumerate() {
+        if i > 0 {
+            w.write_char(' ')?;
+        }
+        match segment {
+            Segment::Move(p) => write!(w, "M {},{}", Coord(p.x), Coord(p.y))?,
+            Segment::Line(p) => write!(w, "L {},{}", Coord(p.x), Coord(p.y))?,
+            Segment::Quad(c, p) => write!(
+                w,
+                "Q {},{} {},{}",
+                Coord(c.x),
+                Coord(c.y),
+                Coord(p.x),
+                Coord(p.y)
+            )?,
+            Segment::Cubic(c, e, p) => write!(
+                w,
+                "C {},{} {},{} {},{}",
+                Coord(c.x),
+                Coord(c.y),
+                Coord(e.x),
+                Coord(e.y),
+                Coord(p.x),
+                Coord(p.y)
+            )?,
+            Segment::Close => w.write_char('Z')?,
+        }
+    }
+    if trailing_space {
+        w.write_char(' ')?;
+    }
+    Ok(())
+}
+
+/// Write one `<path>` element drawing `outline` in `style`
+fn write_path_element<W: Write>(
+    w: &mut W,
+    outline: &[Segment],
+    style: PathStyle,
+    trailing_space: bool,
+    colors: Colors,
+    metrics: &Metrics,
+) -> fmt::Result {
+    w.write_str("<path d=\"")?;
+    write_path_data(w, outline, trailing_space)?;
+    write!(w, "\" fill=\"none\" stroke=\"{}\"", colors.stroke())?;
+    if style.dashed {
+        let (dash, gap) = metrics.dash();
+        write!(w, " stroke-dasharray=\"{},{}\"", Coord(dash), Coord(gap))?;
+    }
+    if style.thick {
+        match colors.thick_width() {
+            Some(width) => write!(w, " stroke-width=\"{}\"", width)?,
+            None => write!(w, " stroke-width=\"{}\"", Coord(metrics.thick_width()))?,
+        }
+    }
+    w.write_str("/>\n")
+}
+
+/// Shape stroking `outline` in `style`
+fn stroke_shape(outline: Vec<Segment>, style: PathStyle, metrics: &Metrics) -> Shape {
+    let mut shape = Shape::stroked(outline, Paint::Stroke);
+    if style.dashed {
+        let (dash, gap) = metrics.dash();
+        shape = shape.with_dash(dash, gap);
+    }
+    if style.thick {
+        shape = shape.with_width(metrics.thick_width());
+    }
+    shape
+}
+
+/// Direction from `from` to `to`
+pub(crate) fn heading(from: Vec2, to: Vec2) -> Vec2 {
+    Vec2::new(to.x - from.x, to.y - from.y)
+}
+
+/// Whether two directions point the same way
+pub(crate) fn is_straight_on(u: Vec2, v: Vec2) -> bool {
+    let cross = u.x * v.y - u.y * v.x;
+    let dot = u.x * v.x + u.y * v.y;
+    cross.abs() <= 1e-9 * dot.abs() && dot > 0.0
+}
+
+/// Key for matching endpoints that coincide
+pub(crate) fn point_key(p: Vec2) -> (i64, i64) {
+    ((p.x * 1000.0).round() as i64, (p.y * 1000.0).round() as i64)
+}
+
+/// A path drawn on its own, or a chain of paths joined end to end
+pub(crate) enum Joined<'a> {
+    Single(&'a Path),
+    /// Outline of the joined paths, which all have this style
+    Chain(PathStyle, Vec<Segment>),
+}
+
+/// Paths of one style by endpoint, for joining
+type EndIndex = HashMap<(PathStyle, (i64, i64)), Vec<usize>>;
+
 /// Line style flags
-#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub struct PathStyle {
     pub dashed: bool,
     pub double: bool,
     pub squiggle: bool,
+    /// Heavy stroke, drawn with `--aasvg-thick-width`
+    pub thick: bool,
 }
 
 /// A path segment: either a straight line or a Bezier curve
@@ -110,6 +352,10 @@ pub struct Path {
     pub d: Option<Vec2>,
     /// Style flags
     pub style: PathStyle,
+    /// For double lines, how far the first rail reaches past `a` and `b`
+    /// and the second rail falls short of them, in rail offsets, so the
+    /// rails meet those of a corner such as `╔`
+    pub(crate) miter: [f64; 2],
 }
 
 impl Path {
@@ -121,6 +367,7 @@ impl Path {
             c: None,
             d: None,
             style: PathStyle::default(),
+            miter: [0.0; 2],
         }
     }
 
@@ -137,6 +384,7 @@ impl Path {
             c: Some(c),
             d: Some(d),
             style: PathStyle::default(),
+            miter: [0.0; 2],
         }
     }
 
@@ -152,12 +400,25 @@ impl Path {
         self
     }
 
+    /// Set the miter of a double line at `a` and `b`: 1 where the first rail
+    /// runs along the outside of a corner, -1 where it runs along the inside
+    pub(crate) fn with_miter(mut self, a: f64, b: f64) -> Self {
+        self.miter = [a, b];
+        self
+    }
+
     /// Set the squiggle style
     pub fn with_squiggle(mut self, squiggle: bool) -> Self {
         self.style.squiggle = squiggle;
         self
     }
 
+    /// Set the thick (heavy) style
+    pub fn with_thick(mut self, thick: bool) -> Self {
+        self.style.thick = thick;
+        self
+    }
+
     /// Returns true if this is a degenerate (zero-length) path
     pub fn is_degenerate(&self) -> bool {
         (self.a.x - self.b.x).abs() < 0.01 && (self.a.y - self.b.y).abs() < 0.01
@@ -353,12 +614,85 @@ impl Path {
     /// Generate SVG path data for this path
     /// Returns a Vec because double lines generate two separate path elements
     pub fn to_svg_paths(&self) -> Vec<String> {
+        self.outlines()
+            .iter()
+            .map(|outline| {
+                let mut d = String::new();
+                let _ = write_path_data(&mut d, outline, self.has_trailing_space());
+                d
+            })
+            .collect()
+    }
+
+    /// JS outputs a trailing space after the last Q command of squiggles
+    fn has_trailing_space(&self) -> bool {
+        self.style.squiggle && self.is_horizontal()
+    }
+
+    /// Write a `<path>` element per outline of this path to `w`
+    pub(crate) fn write_svg<W: Write>(
+        &self,
+        w: &mut W,
+        colors: Colors,
+        metrics: &Metrics,
+    ) -> fmt::Result {
+        // Double lines generate two separate path elements
+        for outline in self.outlines() {
+            let outline = metrics.outline(outline);
+            let trailing_space = self.has_trailing_space();
+            write_path_element(w, &outline, self.style, trailing_space, colors, metrics)?;
+        }
+        Ok(())
+    }
+
+    /// Shapes to draw for this path
+    pub(crate) fn shapes(&self, metrics: &Metrics) -> Vec<Shape> {
+        self.outlines()
+            .into_iter()
+            .map(|outline| stroke_shape(metrics.outline(outline), self.style, metrics))
+            .collect()
+    }
+
+    /// Whether this path can be joined with others into one outline.
+    /// Double and squiggle lines are drawn with outlines of their own.
+    fn is_joinable(&self) -> bool {
+        !(self.style.double || (self.style.squiggle && self.is_horizontal()))
+    }
+
+    /// Start point and outline segment, walking from `b` to `a` if `reversed`
+    pub(crate) fn oriented(&self, reversed: bool) -> (Vec2, Segment) {
+        let (from, to, c, d) = if reversed {
+            (self.b, self.a, self.d, self.c)
+        } else {
+            (self.a, self.b, self.c, self.d)
+        };
+        let segment = match (c, d) {
+            (Some(c), Some(d)) => Segment::Cubic(c, d, to),
+            _ => Segment::Line(to),
+        };
+        (from, segment)
+    }
+
+    /// Directions leaving the start and arriving at the end, walking from
+    /// `b` to `a` if `reversed`
+    fn headings(&self, reversed: bool) -> (Vec2, Vec2) {
+        let (from, segment) = self.oriented(reversed);
+        match segment {
+            Segment::Cubic(c, d, to) => (heading(from, c), heading(d, to)),
+            Segment::Line(to) => (heading(from, to), heading(from, to)),
+            _ => unreachable!("oriented paths are lines or cubics"),
+        }
+    }
+
+    /// Outline segments for this path
+    /// Returns a Vec because double lines are drawn as two separate outlines
+    pub(crate) fn outlines(&self) -> Vec<Vec<Segment>> {
         if self.style.squiggle && self.is_horizontal() {
-            return vec![self.squiggle_svg()];
+            return vec![self.squiggle_outline()];
         }
 
         if self.style.double {
-            // Draw two parallel lines as separate path elements
+            // Draw two parallel lines as separate outlines
             // Compute perpendicular offset matching JS algorithm
             let vx = self.b.x - self.a.x;
             let vy = self.b.y - self.a.y;
@@ -375,57 +709,49 @@ impl Path {
             let offset_x = px * SCALE;
             let offset_y = py * SCALE * ASPECT;
 
-            vec![
-                self.offset_line_svg(offset_x, offset_y),
-                self.offset_line_svg(-offset_x, -offset_y),
-            ]
-        } else {
-            vec![self.single_line_svg()]
-        }
-    }
-
-    fn single_line_svg(&self) -> String {
-        if let (Some(c), Some(d)) = (self.c, self.d) {
-            // Cubic Bezier curve
-            format!(
-                "M {} C {} {} {}",
-                self.a.coords(),
-                c.coords(),
-                d.coords(),
-                self.b.coords()
-            )
+            // Rails ending on a corner reach past the end or fall short of
+            // it by the rail offset, along the line
+            let reach = offset_x.hypot(offset_y);
+            let (ux, uy) = (vx / s * reach, vy / s * reach);
+            let [miter_a, miter_b] = self.miter;
+            let rail = |side: f64| {
+                let mut outline = self.offset_outline(side * offset_x, side * offset_y);
+                if self.c.is_none() {
+                    if let [Segment::Move(a), Segment::Line(b)] = &mut outline[..] {
+                        *a = a.offset_pixels(-side * miter_a * ux, -side * miter_a * uy);
+                        *b = b.offset_pixels(side * miter_b * ux, side * miter_b * uy);
+                    }
+                }
+                outline
+            };
+            vec![rail(1.0), rail(-1.0)]
         } else {
-            // Straight line
-            format!("M {} L {}", self.a.coords(), self.b.coords())
+            vec![self.offset_outline(0.0, 0.0)]
         }
     }
 
-    fn offset_line_svg(&self, dx: f64, dy: f64) -> String {
+    fn offset_outline(&self, dx: f64, dy: f64) -> Vec<Segment> {
         let a = self.a.offset_pixels(dx, dy);
         let b = self.b.offset_pixels(dx, dy);
 
         if let (Some(c), Some(d)) = (self.c, self.d) {
+            // Cubic Bezier curve
             let c = c.offset_pixels(dx, dy);
             let d = d.offset_pixels(dx, dy);
-            format!(
-                "M {} C {} {} {}",
-                a.coords(),
-                c.coords(),
-                d.coords(),
-                b.coords()
-            )
+            vec![Segment::Move(a), Segment::Cubic(c, d, b)]
         } else {
-            format!("M {} L {}", a.coords(), b.coords())
+            // Straight line
+            vec![Segment::Move(a), Segment::Line(b)]
         }
     }
 
-    fn squiggle_svg(&self) -> String {
+    fn squiggle_outline(&self) -> Vec<Segment> {
         // Generate a wavy horizontal line matching JS behavior
         // The JS iterates from A.x to B.x by 1 grid unit, keeping fractional coords
         let y = self.a.y;
         let amplitude = SCALE * ASPECT * 0.2;
 
-        let mut result = format!("M {},{}", format_coord(self.a.x), format_coord(y));
+        let mut result = vec![Segment::Move(Vec2::new(self.a.x, y))];
 
         // Convert to grid coordinates (fractional) for iteration
         // JS: for (let x = x0; x < x1; x++) where x0/x1 are grid coords
@@ -439,57 +765,120 @@ impl Path {
 
         while grid_x < grid_x1 {
             // First half: up to mid
-            let up_x = x + step;
-            let up_y = y - amplitude;
             let mid_x = x + step * 2.0;
-            let _ = write!(
-                result,
-                " Q {},{} {},{}",
-                format_coord(up_x),
-                format_coord(up_y),
-                format_coord(mid_x),
-                format_coord(y)
-            );
+            result.push(Segment::Quad(
+                Vec2::new(x + step, y - amplitude),
+                Vec2::new(mid_x, y),
+            ));
 
             // Second half: down to start
-            let down_x = mid_x + step;
-            let down_y = y + amplitude;
             let next_x = mid_x + step * 2.0;
-            let _ = write!(
-                result,
-                " Q {},{} {},{}",
-                format_coord(down_x),
-                format_coord(down_y),
-                format_coord(next_x),
-                format_coord(y)
-            );
+            result.push(Segment::Quad(
+                Vec2::new(mid_x + step, y + amplitude),
+                Vec2::new(next_x, y),
+            ));
 
             x = next_x;
             grid_x += 1.0;
         }
 
-        // JS outputs a trailing space after the last Q command
-        result.push(' ');
         result
     }
 }
 
 /// Collection of paths with query methods
-#[derive(Debug, Default)]
+///
+/// Paths are indexed by grid cell as they are inserted, so the endpoint and
+/// pass-through queries only test the paths near the queried cell.
+#[derive(Clone, Default)]
 pub struct PathSet {
     paths: Vec<Path>,
+    /// Indices of paths with an endpoint in or next to each cell
+    ends: HashMap<(i32, i32), Vec<usize>>,
+    /// Indices of vertical paths in or next to each column
+    columns: HashMap<i32, Vec<usize>>,
+    /// Indices of horizontal paths in or next to each row
+    rows: HashMap<i32, Vec<usize>>,
+}
+
+// The index is derived data; leave it out so the output stays readable and
+// does not depend on hash map order
+impl fmt::Debug for PathSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PathSet")
+            .field("paths", &self.paths)
+            .finish()
+    }
+}
+
+/// Nearest grid cell of a pixel position
+fn grid_cell(p: Vec2) -> (i32, i32) {
+    (
+        (p.x / SCALE - 1.0).round() as i32,
+        (p.y / (SCALE * ASPECT) - 1.0).round() as i32,
+    )
+}
+
+/// `n` and its two neighbors
+fn around(n: i32) -> [i32; 3] {
+    [n.saturating_sub(1), n, n.saturating_add(1)]
 }
 
 impl PathSet {
     pub fn new() -> Self {
-        Self { paths: Vec::new() }
+        Self::default()
     }
 
     pub fn insert(&mut self, path: Path) {
         // Don't insert degenerate (zero-length) lines
-        if !path.is_degenerate() {
-            self.paths.push(path);
+        if path.is_degenerate() {
+            return;
+        }
+
+        // Every query accepts positions less than half a cell from the
+        // target, so indexing the neighbors of the nearest cell too keeps
+        // lookups exact despite rounding
+        let index = self.paths.len();
+        let (ax, ay) = grid_cell(path.a);
+        let (bx, by) = grid_cell(path.b);
+        for (x, y) in [(ax, ay), (bx, by)] {
+            for nx in around(x) {
+                for ny in around(y) {
+                    let cell = self.ends.entry((nx, ny)).or_default();
+                    if cell.last() != Some(&index) {
+                        cell.push(index);
+                    }
+                }
+            }
+        }
+        if path.is_vertical() {
+            for x in around(ax) {
+                self.columns.entry(x).or_default().push(index);
+            }
+        } else if path.is_horizontal() {
+            for y in around(ay) {
+                self.rows.entry(y).or_default().push(index);
+            }
         }
+
+        self.paths.push(path);
+    }
+
+    /// Paths with an endpoint near the given cell
+    fn ends_near(&self, x: i32, y: i32) -> impl Iterator<Item = &Path> {
+        self.indexed(self.ends.get(&(x, y)))
+    }
+
+    /// Paths with an endpoint near the given fractional position
+    fn ends_near_frac(&self, x: f64, y: f64) -> impl Iterator<Item = &Path> {
+        self.ends_near(x.round() as i32, y.round() as i32)
+    }
+
+    fn indexed<'a>(&'a self, indices: Option<&'a Vec<usize>>) -> impl Iterator<Item = &'a Path> {
+        indices
+            .into_iter()
+            .flatten()
+            .map(move |&index| &self.paths[index])
     }
 
     pub fn iter(&self) -> impl Iterator<Item = &Path> {
@@ -506,90 +895,394 @@ impl PathSet {
 
     /// Check if any path has its top end at the given position
     pub fn up_ends_at(&self, x: i32, y: i32) -> bool {
-        self.paths.iter().any(|p| p.up_ends_at(x, y))
+        self.ends_near(x, y).any(|p| p.up_ends_at(x, y))
     }
 
     /// Check if any path has its top end at the given fractional position
     pub fn up_ends_at_frac(&self, x: f64, y: f64) -> bool {
-        self.paths.iter().any(|p| p.up_ends_at_frac(x, y))
+        self.ends_near_frac(x, y).any(|p| p.up_ends_at_frac(x, y))
     }
 
     /// Check if any path has its bottom end at the given position
     pub fn down_ends_at(&self, x: i32, y: i32) -> bool {
-        self.paths.iter().any(|p| p.down_ends_at(x, y))
+        self.ends_near(x, y).any(|p| p.down_ends_at(x, y))
     }
 
     /// Check if any path has its bottom end at the given fractional position
     pub fn down_ends_at_frac(&self, x: f64, y: f64) -> bool {
-        self.paths.iter().any(|p| p.down_ends_at_frac(x, y))
+        self.ends_near_frac(x, y).any(|p| p.down_ends_at_frac(x, y))
     }
 
     /// Check if any path has its left end at the given position
     pub fn left_ends_at(&self, x: i32, y: i32) -> bool {
-        self.paths.iter().any(|p| p.left_ends_at(x, y))
+        self.ends_near(x, y).any(|p| p.left_ends_at(x, y))
     }
 
     /// Check if any path has its right end at the given position
     pub fn right_ends_at(&self, x: i32, y: i32) -> bool {
-        self.paths.iter().any(|p| p.right_ends_at(x, y))
+        self.ends_near(x, y).any(|p| p.right_ends_at(x, y))
     }
 
     /// Check if any vertical path passes through the given position
     pub fn vertical_passes_through(&self, x: i32, y: i32) -> bool {
-        self.paths.iter().any(|p| p.vertical_passes_through(x, y))
+        self.indexed(self.columns.get(&x))
+            .any(|p| p.vertical_passes_through(x, y))
     }
 
     /// Check if any horizontal path passes through the given position
     pub fn horizontal_passes_through(&self, x: i32, y: i32) -> bool {
-        self.paths.iter().any(|p| p.horizontal_passes_through(x, y))
+        self.indexed(self.rows.get(&y))
+            .any(|p| p.horizontal_passes_through(x, y))
     }
 
     /// Check if any diagonal path ends at the upper position
     pub fn diagonal_up_ends_at(&self, x: i32, y: i32) -> bool {
-        self.paths.iter().any(|p| p.diagonal_up_ends_at(x, y))
+        self.ends_near(x, y).any(|p| p.diagonal_up_ends_at(x, y))
     }
 
     /// Check if any diagonal path ends at the lower position
     pub fn diagonal_down_ends_at(&self, x: i32, y: i32) -> bool {
-        self.paths.iter().any(|p| p.diagonal_down_ends_at(x, y))
+        self.ends_near(x, y).any(|p| p.diagonal_down_ends_at(x, y))
     }
 
     /// Check if any back diagonal path ends at the upper position
     pub fn back_diagonal_up_ends_at(&self, x: i32, y: i32) -> bool {
-        self.paths.iter().any(|p| p.back_diagonal_up_ends_at(x, y))
+        self.ends_near(x, y)
+            .any(|p| p.back_diagonal_up_ends_at(x, y))
     }
 
     /// Check if any back diagonal path ends at the lower position
     pub fn back_diagonal_down_ends_at(&self, x: i32, y: i32) -> bool {
-        self.paths.iter().any(|p| p.back_diagonal_down_ends_at(x, y))
+        self.ends_near(x, y)
+            .any(|p| p.back_diagonal_down_ends_at(x, y))
     }
 
     /// Generate SVG for all paths
     pub fn to_svg(&self) -> String {
+        self.to_svg_with(Colors::Variables)
+    }
+
+    /// Generate SVG for all paths with the given colors
+    pub(crate) fn to_svg_with(&self, colors: Colors) -> String {
         let mut result = String::new();
-        for path in &self.paths {
-            let dash = if path.style.dashed {
-                " stroke-dasharray=\"4,2\""
-            } else {
-                ""
-            };
-            // Double lines generate two separate path elements
-            for path_data in path.to_svg_paths() {
-                let _ = write!(
-                    result,
-                    "<path d=\"{}\" fill=\"none\" stroke=\"var(--aasvg-stroke)\"{}/>\n",
-                    path_data, dash
-                );
+        let _ = self.write_svg(&mut result, colors, false, &Metrics::default());
+        result
+    }
+
+    /// Write a `<path>` element per outline of every path to `w`,
+    /// or per chain of paths with `join`
+    pub(crate) fn write_svg<W: Write>(
+        &self,
+        w: &mut W,
+        colors: Colors,
+        join: bool,
+        metrics: &Metrics,
+    ) -> fmt::Result {
+        if !join {
+            for path in &self.paths {
+                path.write_svg(w, colors, metrics)?;
+            }
+            return Ok(());
+        }
+        for joined in self.joined() {
+            match joined {
+                Joined::Single(path) => path.write_svg(w, colors, metrics)?,
+                Joined::Chain(style, outline) => {
+                    let outline = metrics.outline(outline);
+                    write_path_element(w, &outline, style, false, colors, metrics)?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Shapes for all paths, or for chains of paths with `join`
+    pub(crate) fn shapes(&self, join: bool, metrics: &Metrics) -> Vec<Shape> {
+        if !join {
+            return self
+                .paths
+                .iter()
+                .flat_map(|path| path.shapes(metrics))
+                .collect();
+        }
+        self.joined()
+            .into_iter()
+            .flat_map(|joined| match joined {
+                Joined::Single(path) => path.shapes(metrics),
+                Joined::Chain(style, outline) => {
+                    vec![stroke_shape(metrics.outline(outline), style, metrics)]
+                }
+            })
+            .collect()
+    }
+
+    /// Join paths that meet end to end and share a style into chains.
+    ///
+    /// Chains keep the position of their first path in drawing order. At a
+    /// junction a chain carries straight on if it can, and a chain that
+    /// returns to where it started is closed.
+    pub(crate) fn joined(&self) -> Vec<Joined<'_>> {
+        let mut ends = EndIndex::new();
+        for (index, path) in self.paths.iter().enumerate() {
+            if path.is_joinable() {
+                for p in [path.a, path.b] {
+                    ends.entry((path.style, point_key(p)))
+                        .or_default()
+                        .push(index);
+                }
             }
         }
+
+        let mut used = vec![false; self.paths.len()];
+        let mut result = Vec::new();
+        for (index, path) in self.paths.iter().enumerate() {
+            if used[index] {
+                continue;
+            }
+            used[index] = true;
+            if !path.is_joinable() {
+                result.push(Joined::Single(path));
+                continue;
+            }
+
+            // Path indices, and whether each is walked from `b` to `a`
+            let mut chain = VecDeque::from([(index, false)]);
+            while let Some(&last) = chain.back() {
+                match self.next_in_chain(&ends, &mut used, last, true) {
+                    Some(next) => chain.push_back(next),
+                    None => break,
+                }
+            }
+            while let Some(&first) = chain.front() {
+                match self.next_in_chain(&ends, &mut used, first, false) {
+                    Some(prev) => chain.push_front(prev),
+                    None => break,
+                }
+            }
+            result.push(Joined::Chain(path.style, self.chain_outline(&chain)));
+        }
         result
     }
+
+    /// Claim an unused path continuing the chain after its last piece
+    /// (`forward`) or before its first piece
+    fn next_in_chain(
+        &self,
+        ends: &EndIndex,
+        used: &mut [bool],
+        (index, reversed): (usize, bool),
+        forward: bool,
+    ) -> Option<(usize, bool)> {
+        let path = &self.paths[index];
+        let (leaving, arriving) = path.headings(reversed);
+        // The point to continue from, and the direction of travel there
+        let point = if forward == reversed { path.a } else { path.b };
+        let direction = if forward { arriving } else { leaving };
+        let key = point_key(point);
+
+        let candidates = ends.get(&(path.style, key))?;
+        let mut fallback = None;
+        for &candidate in candidates {
+            if used[candidate] {
+                continue;
+            }
+            let other = &self.paths[candidate];
+            // Walk the candidate away from (forward) or towards the point
+            let reversed = (point_key(other.a) == key) != forward;
+            let (leaving, arriving) = other.headings(reversed);
+            let continues = if forward { leaving } else { arriving };
+            if is_straight_on(direction, continues) {
+                used[candidate] = true;
+                return Some((candidate, reversed));
+            }
+            fallback.get_or_insert((candidate, reversed));
+        }
+        if let Some((candidate, _)) = fallback {
+            used[candidate] = true;
+        }
+        fallback
+    }
+
+    /// Outline of a chain, closed if it ends where it started
+    fn chain_outline(&self, chain: &VecDeque<(usize, bool)>) -> Vec<Segment> {
+        let mut outline = Vec::with_capacity(chain.len() + 1);
+        let mut start = None;
+        let mut end = None;
+        for &(index, reversed) in chain {
+            let (from, segment) = self.paths[index].oriented(reversed);
+            if start.is_none() {
+                start = Some(from);
+                outline.push(Segment::Move(from));
+            }
+            if let Segment::Line(to) | Segment::Cubic(_, _, to) = segment {
+                end = Some(to);
+            }
+            outline.push(segment);
+        }
+
+        let closed = chain.len() > 1 && start.map(point_key) == end.map(point_key);
+        if closed {
+            // `Z` draws the final straight line itself
+            if let Some(Segment::Line(_)) = outline.last() {
+                outline.pop();
+            }
+            outline.push(Segment::Close);
+        }
+        outline
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_path_set_index_matches_scan() {
+        let mut set = PathSet::new();
+        set.insert(Path::line_from_grid(0, 0, 5, 0));
+        set.insert(Path::line_from_grid(2, 1, 2, 6));
+        set.insert(Path::line_from_grid(3, 4, 5, 2));
+        set.insert(Path::line_from_grid(6, 1, 8, 3));
+        set.insert(Path::line(
+            Vec2::from_grid_frac(7.5, 0.0),
+            Vec2::from_grid_frac(7.5, 4.5),
+        ));
+        set.insert(Path::line(
+            Vec2::from_grid_frac(0.0, 5.5),
+            Vec2::from_grid_frac(4.5, 5.5),
+        ));
+        set.insert(Path::curve(
+            Vec2::from_grid(0, 2),
+            Vec2::from_grid(1, 3),
+            Vec2::from_grid(0, 3),
+            Vec2::from_grid(0, 3),
+        ));
+        let paths: Vec<Path> = set.iter().cloned().collect();
+
+        for x in -2..12 {
+            for y in -2..10 {
+                macro_rules! check {
+                    ($query:ident) => {
+                        assert_eq!(
+                            set.$query(x, y),
+                            paths.iter().any(|p| p.$query(x, y)),
+                            "{} at ({}, {})",
+                            stringify!($query),
+                            x,
+                            y
+                        );
+                    };
+                }
+                check!(up_ends_at);
+                check!(down_ends_at);
+                check!(left_ends_at);
+                check!(right_ends_at);
+                check!(vertical_passes_through);
+                check!(horizontal_passes_through);
+                check!(diagonal_up_ends_at);
+                check!(diagonal_down_ends_at);
+                check!(back_diagonal_up_ends_at);
+                check!(back_diagonal_down_ends_at);
+
+                for (fx, fy) in [(x as f64 + 0.5, y as f64), (x as f64, y as f64 + 0.5)] {
+                    assert_eq!(
+                        set.up_ends_at_frac(fx, fy),
+                        paths.iter().any(|p| p.up_ends_at_frac(fx, fy))
+                    );
+                    assert_eq!(
+                        set.down_ends_at_frac(fx, fy),
+                        paths.iter().any(|p| p.down_ends_at_frac(fx, fy))
+                    );
+                }
+            }
+        }
+    }
+
+    /// Outlines of the chains `set` joins into
+    fn chains(set: &PathSet) -> Vec<Vec<Segment>> {
+        set.joined()
+            .into_iter()
+            .map(|joined| match joined {
+                Joined::Chain(_, outline) => outline,
+                Joined::Single(path) => panic!("{:?} was not joined", path),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_join_closes_box() {
+        let mut set = PathSet::new();
+        set.insert(Path::line_from_grid(0, 0, 3, 0));
+        set.insert(Path::line_from_grid(0, 0, 0, 2));
+        set.insert(Path::line_from_grid(3, 0, 3, 2));
+        set.insert(Path::line_from_grid(0, 2, 3, 2));
+
+        let g = Vec2::from_grid;
+        assert_eq!(
+            chains(&set),
+            vec![vec![
+                Segment::Move(g(0, 0)),
+                Segment::Line(g(3, 0)),
+                Segment::Line(g(3, 2)),
+                Segment::Line(g(0, 2)),
+                Segment::Close,
+            ]]
+        );
+    }
+
+    #[test]
+    fn test_join_goes_straight_at_junctions() {
+        let mut set = PathSet::new();
+        set.insert(Path::line_from_grid(0, 1, 2, 1));
+        set.insert(Path::line_from_grid(2, 0, 2, 1));
+        set.insert(Path::line_from_grid(2, 1, 4, 1));
+        set.insert(Path::line_from_grid(2, 1, 2, 2));
+
+        let g = Vec2::from_grid;
+        assert_eq!(
+            chains(&set),
+            vec![
+                vec![
+                    Segment::Move(g(0, 1)),
+                    Segment::Line(g(2, 1)),
+                    Segment::Line(g(4, 1))
+                ],
+                vec![
+                    Segment::Move(g(2, 0)),
+                    Segment::Line(g(2, 1)),
+                    Segment::Line(g(2, 2))
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_join_keeps_styles_apart() {
+        let mut set = PathSet::new();
+        set.insert(Path::line_from_grid(0, 0, 2, 0));
+        set.insert(Path::line_from_grid(2, 0, 4, 0).with_dashed(true));
+        set.insert(Path::line_from_grid(4, 0, 4, 2).with_double(true));
+        let joined = set.joined();
+        assert_eq!(joined.len(), 3);
+        assert!(matches!(joined[2], Joined::Single(_)));
+    }
+
+    #[test]
+    fn test_metrics() {
+        let p = Vec2::from_grid_frac(2.5, 1.0);
+        assert_eq!(Metrics::default().point(p), p);
+
+        let metrics = Metrics::new(10.0, 3.0, 13.0);
+        assert_eq!(metrics.point(p), Vec2::new(35.0, 60.0));
+        assert_eq!(metrics.baseline(), 4.0);
+
+        // A diagonal crosses one cell: 10 across and 30 down
+        let angle = metrics.angle(diagonal_angle());
+        assert!((angle - 3.0f64.atan().to_degrees()).abs() < 1e-9);
+        assert_eq!(metrics.angle(90.0), 90.0);
+        assert_eq!(Metrics::new(16.0, 2.0, 26.0).angle(296.5), 296.5);
+    }
+
     #[test]
     fn test_vec2_from_grid() {
         let v = Vec2::from_grid(0, 0);
@@ -614,6 +1307,19 @@ mod tests {
         assert!(!h.is_vertical());
     }
 
+    #[test]
+    fn test_thick_path_svg() {
+        let mut set = PathSet::new();
+        set.insert(Path::line_from_grid(0, 0, 2, 0).with_thick(true));
+        set.insert(Path::line_from_grid(0, 1, 2, 1));
+        let svg = set.to_svg();
+        assert_eq!(
+            svg.matches("stroke-width=\"var(--aasvg-thick-width)\"")
+                .count(),
+            1
+        );
+    }
+
     #[test]
     fn test_path_svg() {
         let p = Path::line(Vec2::new(10.0, 20.0), Vec2::new(30.0, 40.0));
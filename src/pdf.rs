@@ -0,0 +1,321 @@
+//! Vector PDF output.
+//!
+//! Draws the diagram's [`Shape`]s and text runs straight into a single PDF
+//! page, one SVG pixel to 0.75pt. Text is set in a subset of the bundled Hack
+//! font, so the page looks the same on every machine.
+
+use std::collections::BTreeMap;
+
+use miniz_oxide::deflate::compress_to_vec_zlib;
+use pdf_writer::types::{
+    CidFontType, FontFlags, LineCapStyle, LineJoinStyle, SystemInfo, UnicodeCmap,
+};
+use pdf_writer::{Content, Filter, Finish, Name, Pdf, Rect, Ref, Str};
+use ttf_parser::Face;
+
+use crate::diagram::Diagram;
+use crate::path::Vec2;
+use crate::shape::{quad_to_cubic, Paint, Segment, Shape};
+use crate::svg::{RenderOptions, Viewport};
+use crate::theme::{parse_color, Palette};
+
+/// Hack Regular, used for all text
+const HACK_REGULAR: &[u8] = include_bytes!("../fonts/Hack-Regular.ttf");
+
+/// Points per SVG pixel
+const PT_PER_PX: f32 = 0.75;
+
+const FONT_NAME: Name<'static> = Name(b"F1");
+
+/// Render a parsed diagram to a one-page PDF.
+pub(crate) fn generate_pdf(diagram: &Diagram, options: &RenderOptions) -> Vec<u8> {
+    let palette = options.fixed_palette();
+
+    let viewport = Viewport::new(diagram, options);
+    let (x, y) = (viewport.x as f32, viewport.y as f32);
+    let (width, height) = (viewport.width as f32, viewport.height as f32);
+
+    let catalog_id = Ref::new(1);
+    let page_tree_id = Ref::new(2);
+    let page_id = Ref::new(3);
+    let content_id = Ref::new(4);
+    let font_id = Ref::new(5);
+
+    let mut content = Content::new();
+    // Flip to SVG coordinates: pixels, y pointing down
+    content.transform([
+        PT_PER_PX,
+        0.0,
+        0.0,
+        -PT_PER_PX,
+        -x * PT_PER_PX,
+        (y + height) * PT_PER_PX,
+    ]);
+    content.set_line_cap(LineCapStyle::RoundCap);
+    content.set_line_join(LineJoinStyle::RoundJoin);
+
+    if options.backdrop {
+        if let Some([r, g, b]) = parse_color(&palette.bg) {
+            content.set_fill_rgb(r, g, b);
+            content.rect(x, y, width, height);
+            content.fill_nonzero();
+        }
+    }
+
+    for shape in diagram.shapes(options) {
+        draw_shape(&mut content, &shape, palette);
+    }
+
+    let mut pdf = Pdf::new();
+    let has_text = !options.disable_text && !diagram.text.is_empty();
+    if has_text {
+        let face = Face::parse(HACK_REGULAR, 0).expect("bundled font should parse");
+        let glyphs = draw_text(&mut content, diagram, options, palette, &face);
+        write_font(&mut pdf, font_id, &face, &glyphs);
+    }
+
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.pages(page_tree_id).kids([page_id]).count(1);
+
+    let mut page = pdf.page(page_id);
+    page.media_box(Rect::new(0.0, 0.0, width * PT_PER_PX, height * PT_PER_PX));
+    page.parent(page_tree_id);
+    page.contents(content_id);
+    if has_text {
+        page.resources().fonts().pair(FONT_NAME, font_id);
+    }
+    page.finish();
+
+    let content = compress_to_vec_zlib(&content.finish(), 6);
+    pdf.stream(content_id, &content).filter(Filter::FlateDecode);
+
+    pdf.finish()
+}
+
+/// Append one shape's outline and paint operators
+fn draw_shape(content: &mut Content, shape: &Shape, palette: &Palette) {
+    let fill = shape.fill.and_then(|paint| color(paint, palette));
+    let stroke = shape.stroke.and_then(|paint| color(paint, palette));
+    if fill.is_none() && stroke.is_none() {
+        return;
+    }
+
+    // Graphics state may not change between path construction and painting
+    if let Some([r, g, b]) = fill {
+        content.set_fill_rgb(r, g, b);
+    }
+    if let Some([r, g, b]) = stroke {
+        content.set_stroke_rgb(r, g, b);
+        content.set_line_width(shape.width as f32);
+        match shape.dash {
+            Some((dash, gap)) => content.set_dash_pattern([dash as f32, gap as f32], 0.0),
+            None => content.set_dash_pattern([], 0.0),
+        };
+    }
+
+    let mut current = Vec2::new(0.0, 0.0);
+    for segment in &shape.segments {
+        match *segment {
+            Segment::Move(p) => {
+                content.move_to(p.x as f32, p.y as f32);
+                current = p;
+            }
+            Segment::Line(p) => {
+                content.line_to(p.x as f32, p.y as f32);
+                current = p;
+            }
+            Segment::Quad(c, p) => {
+                let (c1, c2) = quad_to_cubic(current, c, p);
+                content.cubic_to(
+                    c1.x as f32,
+                    c1.y as f32,
+                    c2.x as f32,
+                    c2.y as f32,
+                    p.x as f32,
+                    p.y as f32,
+                );
+                current = p;
+            }
+            Segment::Cubic(c1, c2, p) => {
+                content.cubic_to(
+                    c1.x as f32,
+                    c1.y as f32,
+                    c2.x as f32,
+                    c2.y as f32,
+                    p.x as f32,
+                    p.y as f32,
+                );
+                current = p;
+            }
+            Segment::Close => {
+                content.close_path();
+            }
+        }
+    }
+
+    match (fill, stroke) {
+        (Some(_), Some(_)) => content.fill_nonzero_and_stroke(),
+        (Some(_), None) => content.fill_nonzero(),
+        _ => content.stroke(),
+    };
+}
+
+/// Append the text runs, returning the glyphs used and their characters
+fn draw_text(
+    content: &mut Content,
+    diagram: &Diagram,
+    options: &RenderOptions,
+    palette: &Palette,
+    face: &Face,
+) -> BTreeMap<u16, char> {
+    let mut glyphs = BTreeMap::new();
+    let Some([r, g, b]) = parse_color(&palette.text) else {
+        return glyphs;
+    };
+    let metrics = options.metrics();
+    let font_size = metrics.font_size as f32;
+    let units = font_size / f32::from(face.units_per_em());
+
+    content.set_fill_rgb(r, g, b);
+    content.begin_text();
+    content.set_font(FONT_NAME, font_size);
+
+    for run in &diagram.text {
+        let mut encoded = Vec::new();
+        let mut advance = 0.0;
+        for c in run.text.chars() {
+            let glyph = face.glyph_index(c).unwrap_or_default();
+            advance += f32::from(face.glyph_hor_advance(glyph).unwrap_or(0)) * units;
+            encoded.extend_from_slice(&glyph.0.to_be_bytes());
+            glyphs.entry(glyph.0).or_insert(c);
+        }
+
+        // Centered like `text-anchor="middle"`; stretched text fills its cells
+        let anchor = run.anchor(&metrics);
+        let (width, scaling) = if options.stretch && advance > 0.0 {
+            let width = run.len() as f32 * metrics.cell_width as f32;
+            (width, 100.0 * width / advance)
+        } else {
+            (advance, 100.0)
+        };
+        content.set_horizontal_scaling(scaling);
+        // Flip the glyphs back upright inside the y-down page transform
+        content.set_text_matrix([
+            1.0,
+            0.0,
+            0.0,
+            -1.0,
+            anchor.x as f32 - width / 2.0,
+            anchor.y as f32,
+        ]);
+        content.show(Str(&encoded));
+    }
+
+    content.end_text();
+    glyphs
+}
+
+/// Write the subset font as a Type0 font with Identity-H encoding
+fn write_font(pdf: &mut Pdf, font_id: Ref, face: &Face, glyphs: &BTreeMap<u16, char>) {
+    let cid_font_id = Ref::new(6);
+    let descriptor_id = Ref::new(7);
+    let cmap_id = Ref::new(8);
+    let data_id = Ref::new(9);
+
+    let base_font = Name(b"AASVGA+Hack-Regular");
+    let system_info = SystemInfo {
+        registry: Str(b"Adobe"),
+        ordering: Str(b"Identity"),
+        supplement: 0,
+    };
+    let to_pdf_units = |v: i16| f32::from(v) * 1000.0 / f32::from(face.units_per_em());
+    let advance = |glyph: u16| {
+        let advance = face.glyph_hor_advance(ttf_parser::GlyphId(glyph));
+        f32::from(advance.unwrap_or(0)) * 1000.0 / f32::from(face.units_per_em())
+    };
+
+    pdf.type0_font(font_id)
+        .base_font(base_font)
+        .encoding_predefined(Name(b"Identity-H"))
+        .descendant_font(cid_font_id)
+        .to_unicode(cmap_id);
+
+    let mut cid_font = pdf.cid_font(cid_font_id);
+    cid_font
+        .subtype(CidFontType::Type2)
+        .base_font(base_font)
+        .system_info(system_info)
+        .font_descriptor(descriptor_id)
+        .cid_to_gid_map_predefined(Name(b"Identity"));
+    let mut widths = cid_font.widths();
+    for &glyph in glyphs.keys() {
+        widths.consecutive(glyph, [advance(glyph)]);
+    }
+    widths.finish();
+    cid_font.finish();
+
+    let bbox = face.global_bounding_box();
+    pdf.font_descriptor(descriptor_id)
+        .name(base_font)
+        .flags(FontFlags::FIXED_PITCH | FontFlags::SYMBOLIC)
+        .bbox(Rect::new(
+            to_pdf_units(bbox.x_min),
+            to_pdf_units(bbox.y_min),
+            to_pdf_units(bbox.x_max),
+            to_pdf_units(bbox.y_max),
+        ))
+        .italic_angle(0.0)
+        .ascent(to_pdf_units(face.ascender()))
+        .descent(to_pdf_units(face.descender()))
+        .cap_height(to_pdf_units(
+            face.capital_height().unwrap_or(face.ascender()),
+        ))
+        .stem_v(80.0)
+        .font_file2(data_id);
+
+    let mut cmap = UnicodeCmap::new(Name(b"Custom"), system_info);
+    for (&glyph, &c) in glyphs {
+        cmap.pair(glyph, c);
+    }
+    pdf.cmap(cmap_id, &cmap.finish());
+
+    // Keep the .notdef glyph for characters the font lacks
+    let mut keep: Vec<u16> = glyphs.keys().copied().collect();
+    keep.push(0);
+    let data = subsetter::subset(HACK_REGULAR, 0, subsetter::Profile::pdf(&keep))
+        .unwrap_or_else(|_| HACK_REGULAR.to_vec());
+    let data = compress_to_vec_zlib(&data, 6);
+    pdf.stream(data_id, &data).filter(Filter::FlateDecode);
+}
+
+fn color(paint: Paint, palette: &Palette) -> Option<[f32; 3]> {
+    parse_color(paint.resolve(palette))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theme::Theme;
+
+    #[test]
+    fn test_pdf_page() {
+        let options = RenderOptions::new();
+        let diagram = Diagram::parse("+--+\n|Hi|\n+--+", &options);
+        let pdf = generate_pdf(&diagram, &options);
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.starts_with("%PDF-"));
+        assert!(text.contains("/MediaBox [0 0 30 48]"));
+        assert!(text.contains("/FontFile2"));
+
+        let sized = options.with_size(Some(9), None).with_margin(4.0);
+        let text = String::from_utf8_lossy(&generate_pdf(&diagram, &sized)).into_owned();
+        assert!(text.contains("/MediaBox [0 0 66 54]"));
+    }
+
+    #[test]
+    fn test_pdf_without_text_has_no_font() {
+        let options = RenderOptions::new().with_theme(Theme::github());
+        let diagram = Diagram::parse("+--+\n|  |\n+--+", &options);
+        let pdf = generate_pdf(&diagram, &options);
+        assert!(!String::from_utf8_lossy(&pdf).contains("/Font"));
+    }
+}
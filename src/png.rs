@@ -0,0 +1,160 @@
+//! PNG output via resvg.
+//!
+//! The diagram is rendered to SVG with literal colors (`usvg` does not
+//! resolve CSS variables) and rasterized with the bundled Hack font, so the
+//! result does not depend on the fonts installed on the machine.
+
+use std::fmt;
+
+use resvg::tiny_skia::{Pixmap, Transform};
+use resvg::usvg;
+
+use crate::diagram::Diagram;
+use crate::svg::{generate_svg_with, RenderOptions};
+
+/// Hack Regular, used for `font-family="monospace"` text
+const HACK_REGULAR: &[u8] = include_bytes!("../fonts/Hack-Regular.ttf");
+
+/// Why a diagram could not be rasterized.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PngError {
+    /// The scale is zero, negative or not finite
+    InvalidScale(f32),
+    /// The scaled image is empty or too large to allocate
+    InvalidSize { width: f32, height: f32 },
+    /// The generated SVG was rejected, e.g. because of an attribute set with
+    /// [`RenderOptions::with_attribute`]
+    Svg(String),
+    /// The pixels could not be encoded
+    Encode(String),
+}
+
+impl fmt::Display for PngError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PngError::InvalidScale(scale) => write!(f, "invalid scale {}", scale),
+            PngError::InvalidSize { width, height } => {
+                write!(f, "cannot rasterize a {}x{} image", width, height)
+            }
+            PngError::Svg(e) => write!(f, "invalid SVG: {}", e),
+            PngError::Encode(e) => write!(f, "PNG encoding failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PngError {}
+
+/// Rasterize a parsed diagram to PNG bytes.
+///
+/// Colors come from [`RenderOptions::fixed_palette`]. The image is
+/// transparent unless `options.backdrop` is set. `scale` multiplies the SVG size in pixels.
+pub(crate) fn generate_png(
+    diagram: &Diagram,
+    options: &RenderOptions,
+    scale: f32,
+) -> Result<Vec<u8>, PngError> {
+    if !(scale.is_finite() && scale > 0.0) {
+        return Err(PngError::InvalidScale(scale));
+    }
+    let svg = generate_svg_with(diagram, options, Some(options.fixed_palette()));
+
+    let mut usvg_options = usvg::Options::default();
+    let fontdb = usvg_options.fontdb_mut();
+    fontdb.load_font_data(HACK_REGULAR.to_vec());
+    fontdb.set_monospace_family("Hack");
+
+    let tree =
+        usvg::Tree::from_str(&svg, &usvg_options).map_err(|e| PngError::Svg(e.to_string()))?;
+    let (width, height) = (tree.size().width() * scale, tree.size().height() * scale);
+    let mut pixmap = tree
+        .size()
+        .to_int_size()
+        .scale_by(scale)
+        .and_then(|size| Pixmap::new(size.width(), size.height()))
+        .ok_or(PngError::InvalidSize { width, height })?;
+    resvg::render(
+        &tree,
+        Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    pixmap
+        .encode_png()
+        .map_err(|e| PngError::Encode(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::svg::ColorMode;
+    use crate::theme::{Palette, Theme};
+
+    fn decode_size(png: &[u8]) -> (u32, u32) {
+        let pixmap = Pixmap::decode_png(png).unwrap();
+        (pixmap.width(), pixmap.height())
+    }
+
+    #[test]
+    fn test_png_signature_and_size() {
+        let options = RenderOptions::new();
+        let diagram = Diagram::parse("+--+\n|Hi|\n+--+", &options);
+        let png = generate_png(&diagram, &options, 1.0).unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        assert_eq!(decode_size(&png), (40, 64));
+        assert_eq!(
+            decode_size(&generate_png(&diagram, &options, 2.0).unwrap()),
+            (80, 128)
+        );
+    }
+
+    #[test]
+    fn test_png_palettes() {
+        let options = RenderOptions::new()
+            .with_theme(Theme::classic())
+            .with_backdrop(true);
+        let diagram = Diagram::parse("   ", &options);
+
+        let corner = |options: &RenderOptions| {
+            let png = generate_png(&diagram, options, 1.0).unwrap();
+            let pixel = Pixmap::decode_png(&png).unwrap().pixel(0, 0).unwrap();
+            (pixel.red(), pixel.green(), pixel.blue())
+        };
+        assert_eq!(corner(&options), (0xff, 0xff, 0xff));
+        assert_eq!(
+            corner(&options.clone().with_color_mode(ColorMode::Dark)),
+            (0x1a, 0x1a, 0x1a)
+        );
+
+        // Box fill mixed by `Palette::new`
+        let theme = Theme::new(
+            Palette::new("#000", "#333", "#fff", "#000"),
+            Palette::new("#fff", "#ccc", "#000", "#fff"),
+        );
+        let options = RenderOptions::new().with_theme(theme).with_fill_boxes(true);
+        let diagram = Diagram::parse("+--+\n|  |\n+--+", &options);
+        let png = generate_png(&diagram, &options, 1.0).unwrap();
+        let pixel = Pixmap::decode_png(&png).unwrap().pixel(20, 32).unwrap();
+        assert_eq!(
+            (pixel.red(), pixel.green(), pixel.blue()),
+            (0xeb, 0xeb, 0xeb)
+        );
+    }
+
+    #[test]
+    fn test_png_errors() {
+        let options = RenderOptions::new();
+        let diagram = Diagram::parse("--", &options);
+        assert_eq!(
+            generate_png(&diagram, &options, 0.0),
+            Err(PngError::InvalidScale(0.0))
+        );
+        assert!(matches!(
+            generate_png(&diagram, &options, f32::NAN),
+            Err(PngError::InvalidScale(_))
+        ));
+        assert!(matches!(
+            generate_png(&diagram, &options, 1e9),
+            Err(PngError::InvalidSize { .. })
+        ));
+    }
+}
@@ -0,0 +1,574 @@
+//! Closed regions such as boxes, found from the detected paths.
+//!
+//! The finders only know about single lines and curves; a box is four of
+//! them that happen to meet at the corners. [`find_regions`] joins the paths
+//! into a planar graph, walks the faces of that graph and keeps those shaped
+//! like boxes, so they can be filled under the line work.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Write};
+
+use crate::path::{
+    heading, is_straight_on, point_key, write_path_data, Coord, Metrics, PathSet, Vec2,
+};
+use crate::shape::{Paint, Segment, Shape};
+
+/// Tolerance in pixels for points lying on a line, as in [`crate::Path`]
+const EPSILON: f64 = 0.01;
+
+/// A closed area bounded by paths: a box, possibly with rounded corners or
+/// round ends.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    /// Top-left corner of the rectangle spanned by the sides, in pixels
+    pub min: Vec2,
+    /// Bottom-right corner of the rectangle spanned by the sides, in pixels
+    pub max: Vec2,
+    /// Closed outline along the bounding paths
+    outline: Vec<Segment>,
+}
+
+impl Region {
+    /// Returns true if curves round any of the corners or ends
+    pub fn is_rounded(&self) -> bool {
+        self.outline
+            .iter()
+            .any(|segment| matches!(segment, Segment::Cubic(..)))
+    }
+
+    /// Write a `<rect>`, or a `<path>` for rounded regions, without paint
+    /// attributes so the enclosing group's fill applies
+    pub(crate) fn write_svg<W: Write>(&self, w: &mut W, metrics: &Metrics) -> fmt::Result {
+        if self.is_rounded() {
+            w.write_str("<path d=\"")?;
+            write_path_data(w, &metrics.outline(self.outline.clone()), false)?;
+            return w.write_str("\"/>\n");
+        }
+        let (min, max) = (metrics.point(self.min), metrics.point(self.max));
+        writeln!(
+            w,
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>",
+            Coord(min.x),
+            Coord(min.y),
+            Coord(max.x - min.x),
+            Coord(max.y - min.y)
+        )
+    }
+
+    /// Bounds including the curves' control points, which is where round
+    /// ends bulge out past `min` and `max`
+    pub(crate) fn extent(&self) -> (Vec2, Vec2) {
+        let (mut min, mut max) = (self.min, self.max);
+        for segment in &self.outline {
+            if let Segment::Cubic(c, d, _) = *segment {
+                for p in [c, d] {
+                    min = Vec2::new(min.x.min(p.x), min.y.min(p.y));
+                    max = Vec2::new(max.x.max(p.x), max.y.max(p.y));
+                }
+            }
+        }
+        (min, max)
+    }
+
+    /// Fill shape for this region
+    pub(crate) fn shape(&self, metrics: &Metrics) -> Shape {
+        Shape::filled(metrics.outline(self.outline.clone()), Paint::BoxFill)
+    }
+}
+
+/// The paths as a planar graph: vertices where paths end, meet or cross,
+/// and the pieces of path between them
+pub(crate) struct Planar {
+    pub(crate) vertices: Vec<Vec2>,
+    pub(crate) pieces: Vec<Piece>,
+}
+
+/// One piece of a path between two graph vertices
+pub(crate) struct Piece {
+    pub(crate) from: usize,
+    pub(crate) to: usize,
+    /// `Line` or `Cubic` ending at `to`
+    pub(crate) segment: Segment,
+}
+
+impl Piece {
+    /// Segment walking from `to` back to `from`
+    fn reversed(&self, vertices: &[Vec2]) -> Segment {
+        let from = vertices[self.from];
+        match self.segment {
+            Segment::Cubic(c, d, _) => Segment::Cubic(d, c, from),
+            _ => Segment::Line(from),
+        }
+    }
+}
+
+/// Half of a piece, walked from `pieces[i / 2].from` for even `i` and
+/// backwards for odd `i`
+type HalfEdge = usize;
+
+/// Find the closed regions shaped like boxes, in reading order.
+///
+/// A region's straight sides must be horizontal or vertical and lie on its
+/// bounding rectangle, with straight sides on two opposite sides of it.
+/// Curves may round the corners or close the ends, as in `.--.` or `(  )`.
+pub(crate) fn find_regions(paths: &PathSet) -> Vec<Region> {
+    let graph = Planar::new(paths);
+    region_faces(&graph)
+        .into_iter()
+        .map(|(region, _)| region)
+        .collect()
+}
+
+/// Regions of `graph` as by [`find_regions`], each with the indices of the
+/// pieces around it
+pub(crate) fn region_faces(graph: &Planar) -> Vec<(Region, Vec<usize>)> {
+    let Planar { vertices, pieces } = graph;
+    let bridges = find_bridges(vertices.len(), pieces);
+
+    // Outgoing half-edges around each vertex, sorted by angle
+    let mut outgoing: Vec<Vec<(f64, HalfEdge)>> = vec![Vec::new(); vertices.len()];
+    for (i, piece) in pieces.iter().enumerate() {
+        if bridges[i] {
+            continue;
+        }
+        let (leaving, arriving) = tangents(vertices[piece.from], piece.segment);
+        outgoing[piece.from].push((leaving.y.atan2(leaving.x), 2 * i));
+        outgoing[piece.to].push((arriving.y.atan2(arriving.x), 2 * i + 1));
+    }
+    for around in &mut outgoing {
+        around.sort_by(|a, b| a.0.total_cmp(&b.0));
+    }
+    let origin = |h: HalfEdge| {
+        let piece = &pieces[h / 2];
+        if h % 2 == 0 {
+            piece.from
+        } else {
+            piece.to
+        }
+    };
+
+    // Each face lies to the right of its half-edges on screen, so inner
+    // faces wind clockwise and have positive area with y pointing down
+    let mut visited = vec![false; pieces.len() * 2];
+    let mut regions = Vec::new();
+    for start in 0..pieces.len() * 2 {
+        if visited[start] || bridges[start / 2] {
+            continue;
+        }
+        let mut face = Vec::new();
+        let mut h = start;
+        while !visited[h] {
+            visited[h] = true;
+            face.push(h);
+            // Take the sharpest right turn: the piece before the way back,
+            // as angles grow clockwise on screen
+            let twin = h ^ 1;
+            let around = &outgoing[origin(twin)];
+            let position = around.iter().position(|&(_, o)| o == twin).unwrap_or(0);
+            h = around[(position + around.len() - 1) % around.len()].1;
+        }
+        if let Some(region) = face_region(&face, vertices, pieces, origin) {
+            let around = face.iter().map(|&h| h / 2).collect();
+            regions.push((region, around));
+        }
+    }
+
+    regions.sort_by(|(a, _), (b, _)| {
+        (a.min.y, a.min.x)
+            .partial_cmp(&(b.min.y, b.min.x))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    regions
+}
+
+impl Planar {
+    /// Split the paths where they end on or cross each other
+    pub(crate) fn new(paths: &PathSet) -> Self {
+        let mut vertices = Vec::new();
+        let mut ids = HashMap::new();
+        let mut vertex = |p: Vec2| {
+            *ids.entry(point_key(p)).or_insert_with(|| {
+                vertices.push(p);
+                vertices.len() - 1
+            })
+        };
+
+        let paths: Vec<_> = paths.iter().filter(|path| !path.is_degenerate()).collect();
+
+        // Path ends by row and column, to split straight lines where others meet them
+        let mut rows: HashMap<i64, Vec<f64>> = HashMap::new();
+        let mut columns: HashMap<i64, Vec<f64>> = HashMap::new();
+        for path in &paths {
+            for p in [path.a, path.b] {
+                rows.entry(point_key(p).1).or_default().push(p.x);
+                columns.entry(point_key(p).0).or_default().push(p.y);
+            }
+        }
+        // Straight lines by position across them, to split lines where they cross
+        let mut verticals: Vec<_> = paths.iter().filter(|path| path.is_vertical()).collect();
+        verticals.sort_by(|a, b| a.a.x.total_cmp(&b.a.x));
+        let mut horizontals: Vec<_> = paths.iter().filter(|path| path.is_horizontal()).collect();
+        horizontals.sort_by(|a, b| a.a.y.total_cmp(&b.a.y));
+
+        let mut pieces = Vec::new();
+        let mut straight = HashSet::new();
+        for path in &paths {
+            let (start, segment) = path.oriented(false);
+            let Segment::Line(end) = segment else {
+                pieces.push(Piece {
+                    from: vertex(start),
+                    to: vertex(end_of(segment)),
+                    segment,
+                });
+                continue;
+            };
+
+            // Stops along the line, as distances from its start
+            let mut stops = vec![0.0, distance(start, end)];
+            if path.is_horizontal() {
+                let (x0, x1) = (start.x.min(end.x), start.x.max(end.x));
+                let inside = |x: f64| x > x0 + EPSILON && x < x1 - EPSILON;
+                if let Some(xs) = rows.get(&point_key(start).1) {
+                    stops.extend(
+                        xs.iter()
+                            .filter(|&&x| inside(x))
+                            .map(|x| (x - start.x).abs()),
+                    );
+                }
+                let first = verticals.partition_point(|v| v.a.x <= x0 + EPSILON);
+                for v in verticals[first..].iter().take_while(|v| inside(v.a.x)) {
+                    if spans(v.a.y, v.b.y, start.y) {
+                        stops.push((v.a.x - start.x).abs());
+                    }
+                }
+            } else if path.is_vertical() {
+                let (y0, y1) = (start.y.min(end.y), start.y.max(end.y));
+                let inside = |y: f64| y > y0 + EPSILON && y < y1 - EPSILON;
+                if let Some(ys) = columns.get(&point_key(start).0) {
+                    stops.extend(
+                        ys.iter()
+                            .filter(|&&y| inside(y))
+                            .map(|y| (y - start.y).abs()),
+                    );
+                }
+                let first = horizontals.partition_point(|h| h.a.y <= y0 + EPSILON);
+                for h in horizontals[first..].iter().take_while(|h| inside(h.a.y)) {
+                    if spans(h.a.x, h.b.x, start.x) {
+                        stops.push((h.a.y - start.y).abs());
+                    }
+                }
+            }
+            stops.sort_by(f64::total_cmp);
+
+            let length = distance(start, end);
+            let mut from = vertex(start);
+            for &stop in &stops[1..] {
+                let t = stop / length;
+                let p = Vec2::new(
+                    start.x + (end.x - start.x) * t,
+                    start.y + (end.y - start.y) * t,
+                );
+                let to = vertex(p);
+                // Overlapping lines and repeated stops give the same piece again
+                if from != to && straight.insert((from.min(to), from.max(to))) {
+                    pieces.push(Piece {
+                        from,
+                        to,
+                        segment: Segment::Line(p),
+                    });
+                }
+                from = to;
+            }
+        }
+        Self { vertices, pieces }
+    }
+}
+
+/// Directions leaving each end of a segment from `from`, towards the other
+fn tangents(from: Vec2, segment: Segment) -> (Vec2, Vec2) {
+    match segment {
+        Segment::Cubic(c, d, to) => {
+            // A control point on its end point gives no direction
+            let first = if point_key(c) == point_key(from) {
+                d
+            } else {
+                c
+            };
+            let last = if point_key(d) == point_key(to) { c } else { d };
+            (heading(from, first), heading(to, last))
+        }
+        Segment::Line(to) => (heading(from, to), heading(to, from)),
+        _ => unreachable!("pieces are lines or cubics"),
+    }
+}
+
+fn end_of(segment: Segment) -> Vec2 {
+    match segment {
+        Segment::Move(p) | Segment::Line(p) | Segment::Quad(_, p) | Segment::Cubic(_, _, p) => p,
+        Segment::Close => unreachable!("pieces are lines or cubics"),
+    }
+}
+
+/// Whether `v` lies between `a` and `b`, ends included
+fn spans(a: f64, b: f64, v: f64) -> bool {
+    a.min(b) <= v + EPSILON && a.max(b) >= v - EPSILON
+}
+
+fn distance(a: Vec2, b: Vec2) -> f64 {
+    (b.x - a.x).hypot(b.y - a.y)
+}
+
+/// Mark the pieces whose removal disconnects the graph. They bound no face
+/// on either side, such as a line leading to a box or hanging into it.
+fn find_bridges(vertex_count: usize, pieces: &[Piece]) -> Vec<bool> {
+    let mut adjacent = vec![Vec::new(); vertex_count];
+    for (i, piece) in pieces.iter().enumerate() {
+        adjacent[piece.from].push((piece.to, i));
+        adjacent[piece.to].push((piece.from, i));
+    }
+
+    // Iterative Tarjan: discovery order and the lowest order reachable
+    // through a back edge, per vertex
+    let mut order = vec![usize::MAX; vertex_count];
+    let mut low = vec![0; vertex_count];
+    let mut bridges = vec![false; pieces.len()];
+    let mut counter = 0;
+    for root in 0..vertex_count {
+        if order[root] != usize::MAX {
+            continue;
+        }
+        order[root] = counter;
+        low[root] = counter;
+        counter += 1;
+        // Vertex, piece it was reached by, next neighbour to visit
+        let mut stack = vec![(root, usize::MAX, 0)];
+        while let Some(&(v, parent, next)) = stack.last() {
+            if let Some(&(w, piece)) = adjacent[v].get(next) {
+                if let Some(top) = stack.last_mut() {
+                    top.2 += 1;
+                }
+                if piece == parent {
+                    continue;
+                }
+                if order[w] == usize::MAX {
+                    order[w] = counter;
+                    low[w] = counter;
+                    counter += 1;
+                    stack.push((w, piece, 0));
+                } else {
+                    low[v] = low[v].min(order[w]);
+                }
+            } else {
+                stack.pop();
+                if let Some(&(u, _, _)) = stack.last() {
+                    low[u] = low[u].min(low[v]);
+                    if low[v] > order[u] {
+                        bridges[parent] = true;
+                    }
+                }
+            }
+        }
+    }
+    bridges
+}
+
+/// The region enclosed by `face`, if it is an inner face shaped like a box
+fn face_region(
+    face: &[HalfEdge],
+    vertices: &[Vec2],
+    pieces: &[Piece],
+    origin: impl Fn(HalfEdge) -> usize,
+) -> Option<Region> {
+    let segment = |h: HalfEdge| {
+        let piece = &pieces[h / 2];
+        if h % 2 == 0 {
+            piece.segment
+        } else {
+            piece.reversed(vertices)
+        }
+    };
+
+    // Outer faces wind the other way; curves count with their midpoints
+    let mut points = Vec::with_capacity(face.len());
+    for &h in face {
+        let from = vertices[origin(h)];
+        points.push(from);
+        if let Segment::Cubic(c, d, to) = segment(h) {
+            points.push(Vec2::new(
+                (from.x + 3.0 * c.x + 3.0 * d.x + to.x) / 8.0,
+                (from.y + 3.0 * c.y + 3.0 * d.y + to.y) / 8.0,
+            ));
+        }
+    }
+    let area: f64 = (0..points.len())
+        .map(|i| {
+            let (p, q) = (points[i], points[(i + 1) % points.len()]);
+            p.x * q.y - q.x * p.y
+        })
+        .sum();
+    if area <= EPSILON {
+        return None;
+    }
+
+    // A face touching itself at a vertex is not a simple box
+    let mut seen = HashSet::new();
+    if !face.iter().all(|&h| seen.insert(origin(h))) {
+        return None;
+    }
+
+    let corners = face.iter().map(|&h| vertices[origin(h)]);
+    let min = corners.clone().fold(Vec2::new(f64::MAX, f64::MAX), |m, p| {
+        Vec2::new(m.x.min(p.x), m.y.min(p.y))
+    });
+    let max = corners.fold(Vec2::new(f64::MIN, f64::MIN), |m, p| {
+        Vec2::new(m.x.max(p.x), m.y.max(p.y))
+    });
+    if max.x - min.x <= EPSILON || max.y - min.y <= EPSILON {
+        return None;
+    }
+
+    // Straight pieces must run along the bounding rectangle
+    let near = |a: f64, b: f64| (a - b).abs() <= EPSILON;
+    let (mut top, mut bottom, mut left, mut right) = (false, false, false, false);
+    for &h in face {
+        let Segment::Line(to) = segment(h) else {
+            continue;
+        };
+        let from = vertices[origin(h)];
+        if near(from.y, to.y) && near(from.y, min.y) {
+            top = true;
+        } else if near(from.y, to.y) && near(from.y, max.y) {
+            bottom = true;
+        } else if near(from.x, to.x) && near(from.x, min.x) {
+            left = true;
+        } else if near(from.x, to.x) && near(from.x, max.x) {
+            right = true;
+        } else {
+            return None;
+        }
+    }
+    if !(top && bottom || left && right) {
+        return None;
+    }
+
+    // Start at the top-left corner and merge lines that carry straight on
+    let first = (0..face.len())
+        .min_by(|&i, &j| {
+            let (p, q) = (vertices[origin(face[i])], vertices[origin(face[j])]);
+            (p.y, p.x)
+                .partial_cmp(&(q.y, q.x))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+        .unwrap_or(0);
+    let start = vertices[origin(face[first])];
+    let mut outline = vec![Segment::Move(start)];
+    // Start and end of the last segment in the outline
+    let (mut from, mut current) = (start, start);
+    for i in 0..face.len() {
+        let next = segment(face[(first + i) % face.len()]);
+        let to = end_of(next);
+        if let (Segment::Line(_), Some(Segment::Line(end))) = (next, outline.last_mut()) {
+            if is_straight_on(heading(from, *end), heading(*end, to)) {
+                *end = to;
+                current = to;
+                continue;
+            }
+        }
+        outline.push(next);
+        from = current;
+        current = to;
+    }
+    // The closing line back to the start is implied by `Z`
+    if let Some(Segment::Line(end)) = outline.last() {
+        if point_key(*end) == point_key(start) {
+            outline.pop();
+        }
+    }
+    outline.push(Segment::Close);
+
+    Some(Region { min, max, outline })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagram::Diagram;
+    use crate::path::{ASPECT, SCALE};
+    use crate::svg::RenderOptions;
+
+    fn regions(input: &str) -> Vec<Region> {
+        Diagram::parse(input, &RenderOptions::new()).regions
+    }
+
+    /// Grid cells of the corners of each region
+    fn corners(input: &str) -> Vec<(f64, f64, f64, f64)> {
+        let cell = |p: Vec2| (p.x / SCALE - 1.0, p.y / (SCALE * ASPECT) - 1.0);
+        regions(input)
+            .iter()
+            .map(|r| {
+                let ((x0, y0), (x1, y1)) = (cell(r.min), cell(r.max));
+                (x0, y0, x1, y1)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_find_box() {
+        let found = regions("+--+\n|  |\n+--+");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].min, Vec2::new(8.0, 16.0));
+        assert_eq!(found[0].max, Vec2::new(32.0, 48.0));
+        assert!(!found[0].is_rounded());
+
+        let mut svg = String::new();
+        found[0].write_svg(&mut svg, &Metrics::default()).unwrap();
+        assert_eq!(svg, "<rect x=\"8\" y=\"16\" width=\"24\" height=\"32\"/>\n");
+    }
+
+    #[test]
+    fn test_shared_wall_and_crossing() {
+        assert_eq!(
+            corners("+--+--+\n|  |  |\n+--+--+"),
+            vec![(0.0, 0.0, 3.0, 2.0), (3.0, 0.0, 6.0, 2.0)]
+        );
+        // The middle line crosses both walls without ending on them
+        assert_eq!(corners("  |\n+-+-+\n| | |\n+-+-+\n  |").len(), 2);
+    }
+
+    #[test]
+    fn test_nested_and_connected_boxes() {
+        let input = "\
++--------+
+| +--+   |    +--+
+| |  |---+----|  |
+| +--+   |    +--+
++--------+";
+        assert_eq!(
+            corners(input),
+            vec![
+                (0.0, 0.0, 9.0, 4.0),
+                (2.0, 1.0, 5.0, 3.0),
+                (14.0, 1.0, 17.0, 3.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rounded_boxes() {
+        for input in ["╭──╮\n│  │\n╰──╯", " .--.\n(    )\n '--'"] {
+            let found = regions(input);
+            assert_eq!(found.len(), 1, "{}", input);
+            assert!(found[0].is_rounded());
+            assert!(matches!(found[0].outline[0], Segment::Move(_)));
+            assert_eq!(found[0].outline.last(), Some(&Segment::Close));
+        }
+    }
+
+    #[test]
+    fn test_other_shapes_are_not_boxes() {
+        // Open box, L shape and parallelogram
+        assert!(regions("+--+\n|  |\n+  +").is_empty());
+        assert!(regions("+--+\n|  |\n|  +--+\n|     |\n+-----+").is_empty());
+        assert!(regions("  +---+\n /   /\n+---+").is_empty());
+    }
+}
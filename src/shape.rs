@@ -0,0 +1,192 @@
+//! Backend-neutral drawing primitives.
+//!
+//! Paths and decorations describe their geometry as [`Shape`]s in absolute
+//! pixel coordinates, with colors given as [`Paint`] roles. Output formats
+//! other than SVG draw from these instead of re-deriving the geometry.
+
+use crate::path::{Vec2, CURVE};
+use crate::theme::Palette;
+
+/// One command of an outline
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Segment {
+    Move(Vec2),
+    Line(Vec2),
+    /// Quadratic Bezier: control point, end point
+    Quad(Vec2, Vec2),
+    /// Cubic Bezier: two control points, end point
+    Cubic(Vec2, Vec2, Vec2),
+    Close,
+}
+
+/// Palette color a shape is painted with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Paint {
+    Stroke,
+    Fill,
+    Bg,
+    /// Gray level from 1 to 4
+    Gray(u8),
+    Shade,
+    BoxFill,
+}
+
+impl Paint {
+    /// The palette value for this role
+    #[cfg_attr(not(feature = "pdf"), allow(dead_code))]
+    pub(crate) fn resolve(self, palette: &Palette) -> &str {
+        match self {
+            Paint::Stroke => &palette.stroke,
+            Paint::Fill => &palette.fill,
+            Paint::Bg => &palette.bg,
+            Paint::Gray(level) => &palette.grays[usize::from(level.clamp(1, 4) - 1)],
+            Paint::Shade => &palette.shade,
+            Paint::BoxFill => &palette.box_fill,
+        }
+    }
+}
+
+/// A filled and/or stroked outline
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Shape {
+    pub segments: Vec<Segment>,
+    pub fill: Option<Paint>,
+    pub stroke: Option<Paint>,
+    /// Stroke width in pixels
+    pub width: f64,
+    /// Dash and gap lengths in pixels
+    pub dash: Option<(f64, f64)>,
+}
+
+impl Shape {
+    /// A 1px stroke with no fill
+    pub(crate) fn stroked(segments: Vec<Segment>, paint: Paint) -> Self {
+        Self {
+            segments,
+            fill: None,
+            stroke: Some(paint),
+            width: 1.0,
+            dash: None,
+        }
+    }
+
+    /// A fill with no stroke
+    pub(crate) fn filled(segments: Vec<Segment>, paint: Paint) -> Self {
+        Self {
+            segments,
+            fill: Some(paint),
+            stroke: None,
+            width: 1.0,
+            dash: None,
+        }
+    }
+
+    pub(crate) fn with_stroke(mut self, paint: Paint) -> Self {
+        self.stroke = Some(paint);
+        self
+    }
+
+    pub(crate) fn with_width(mut self, width: f64) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub(crate) fn with_dash(mut self, dash: f64, gap: f64) -> Self {
+        self.dash = Some((dash, gap));
+        self
+    }
+}
+
+/// Closed polygon through `points`
+pub(crate) fn polygon(points: &[Vec2]) -> Vec<Segment> {
+    let mut segments = Vec::with_capacity(points.len() + 1);
+    for (i, &p) in points.iter().enumerate() {
+        segments.push(if i == 0 {
+            Segment::Move(p)
+        } else {
+            Segment::Line(p)
+        });
+    }
+    segments.push(Segment::Close);
+    segments
+}
+
+/// Circle approximated by four cubic Bezier arcs
+pub(crate) fn circle(center: Vec2, r: f64) -> Vec<Segment> {
+    let k = r * CURVE;
+    let (cx, cy) = (center.x, center.y);
+    vec![
+        Segment::Move(Vec2::new(cx + r, cy)),
+        Segment::Cubic(
+            Vec2::new(cx + r, cy + k),
+            Vec2::new(cx + k, cy + r),
+            Vec2::new(cx, cy + r),
+        ),
+        Segment::Cubic(
+            Vec2::new(cx - k, cy + r),
+            Vec2::new(cx - r, cy + k),
+            Vec2::new(cx - r, cy),
+        ),
+        Segment::Cubic(
+            Vec2::new(cx - r, cy - k),
+            Vec2::new(cx - k, cy - r),
+            Vec2::new(cx, cy - r),
+        ),
+        Segment::Cubic(
+            Vec2::new(cx + k, cy - r),
+            Vec2::new(cx + r, cy - k),
+            Vec2::new(cx + r, cy),
+        ),
+        Segment::Close,
+    ]
+}
+
+/// Control points of the cubic Bezier equal to a quadratic one from `from`
+pub(crate) fn quad_to_cubic(from: Vec2, c: Vec2, to: Vec2) -> (Vec2, Vec2) {
+    let c1 = Vec2::new(
+        from.x + 2.0 / 3.0 * (c.x - from.x),
+        from.y + 2.0 / 3.0 * (c.y - from.y),
+    );
+    let c2 = Vec2::new(
+        to.x + 2.0 / 3.0 * (c.x - to.x),
+        to.y + 2.0 / 3.0 * (c.y - to.y),
+    );
+    (c1, c2)
+}
+
+/// Rotate `p` by `degrees` (clockwise on screen, like SVG) around `center`
+pub(crate) fn rotate(p: Vec2, center: Vec2, degrees: f64) -> Vec2 {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let dx = p.x - center.x;
+    let dy = p.y - center.y;
+    Vec2::new(
+        center.x + dx * cos - dy * sin,
+        center.y + dx * sin + dy * cos,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotate() {
+        let p = rotate(Vec2::new(2.0, 0.0), Vec2::new(1.0, 0.0), 90.0);
+        assert!((p.x - 1.0).abs() < 1e-9);
+        assert!((p.y - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_circle_extent() {
+        let segments = circle(Vec2::new(10.0, 10.0), 6.0);
+        assert_eq!(segments[0], Segment::Move(Vec2::new(16.0, 10.0)));
+        assert_eq!(segments.last(), Some(&Segment::Close));
+    }
+
+    #[test]
+    fn test_paint_resolve() {
+        let theme = crate::theme::Theme::classic();
+        assert_eq!(Paint::Stroke.resolve(&theme.dark), "#fff");
+        assert_eq!(Paint::Gray(4).resolve(&theme.light), "#000");
+    }
+}
@@ -1,29 +1,246 @@
 //! SVG generation with CSS variables for light/dark mode support.
 
-use std::fmt::Write;
-
-use crate::decoration::DecorationSet;
-use crate::grid::{unhide_markers, Grid};
-use crate::path::{PathSet, ASPECT, SCALE};
-
-/// CSS style block for light/dark mode support
-const CSS_VARIABLES: &str = r#"<style>
-  :root {
-    --aasvg-stroke: #000;
-    --aasvg-fill: #000;
-    --aasvg-bg: #fff;
-    --aasvg-text: #000;
-  }
-  @media (prefers-color-scheme: dark) {
-    :root {
-      --aasvg-stroke: #fff;
-      --aasvg-fill: #fff;
-      --aasvg-bg: #1a1a1a;
-      --aasvg-text: #fff;
-    }
-  }
-</style>
-"#;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::io;
+
+use crate::diagram::{Diagram, TextRun};
+use crate::path::{Coord, Metrics, Vec2, ASPECT, FONT_SIZE, SCALE};
+use crate::theme::{is_css_safe, Colors, Palette, Theme};
+
+/// Which elements the `--aasvg-*` CSS variables are set on.
+///
+/// The default, [`CssScope::Root`], sets them on the document root. When
+/// several diagrams with different themes are inlined into one HTML page,
+/// scope the variables to each SVG instead so they neither collide nor leak
+/// into the host page.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum CssScope {
+    /// `:root`, shared with the host page
+    #[default]
+    Root,
+    /// `svg.diagram`, every diagram on the page
+    Diagram,
+    /// `svg#<id>` with an id derived from the diagram and options,
+    /// so each SVG carries its own variables.
+    ///
+    /// The id is a hash of the content, not a counter: the same diagram
+    /// rendered twice with the same options gets the same id. That is
+    /// harmless for the variables, which are then identical too, but use
+    /// [`CssScope::Id`] when the copies must be told apart.
+    Unique,
+    /// `svg#<id>` with the given id, which must be a valid CSS identifier
+    Id(String),
+}
+
+/// Text of the `<desc>` element, which screen readers announce after the
+/// title.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Description {
+    /// No `<desc>` element
+    #[default]
+    None,
+    /// The diagram's text runs in reading order, separated by commas.
+    /// Runs without letters or digits, such as stray punctuation, are left
+    /// out, and so is the element when nothing remains.
+    Auto,
+    /// The given text
+    Text(String),
+}
+
+/// Where the original characters are drawn, as faint text in their cells.
+///
+/// The overlay is a `<g class="aasvg-source">`, so a page can hide it with
+/// `.aasvg-source { display: none; }` and show it again by toggling a class.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SourceOverlay {
+    /// No overlay
+    #[default]
+    None,
+    /// Under the lines and text
+    Under,
+    /// On top of the lines and text
+    Over,
+}
+
+/// Selector the CSS variables are set on
+enum Selector<'a> {
+    Root,
+    Diagram,
+    Id(SvgId<'a>),
+}
+
+/// `id` attribute of the root `<svg>` element
+#[derive(Clone, Copy)]
+enum SvgId<'a> {
+    /// Hash of the diagram and options
+    Unique(u64),
+    Given(&'a str),
+}
+
+impl fmt::Display for Selector<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Selector::Root => f.write_str(":root"),
+            Selector::Diagram => f.write_str("svg.diagram"),
+            Selector::Id(SvgId::Unique(hash)) => write!(f, "svg#aasvg-{:016x}", hash),
+            Selector::Id(SvgId::Given(id)) => {
+                // Escaped into a CSS identifier, which cannot contain most
+                // punctuation or start with a digit
+                f.write_str("svg#")?;
+                let leading = if id.starts_with('-') { 2 } else { 1 };
+                for (i, c) in id.chars().enumerate() {
+                    let plain = c.is_ascii_alphabetic()
+                        || c == '_'
+                        || c == '-'
+                        || !c.is_ascii()
+                        || (c.is_ascii_digit() && i >= leading);
+                    if plain {
+                        write!(f, "{}", c)?;
+                    } else {
+                        write!(f, "\\{:x} ", u32::from(c))?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for SvgId<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SvgId::Unique(hash) => write!(f, "aasvg-{:016x}", hash),
+            SvgId::Given(id) => f.write_str(id),
+        }
+    }
+}
+
+/// Resolve the CSS selector for a diagram
+fn css_selector<'a>(diagram: &Diagram, options: &'a RenderOptions) -> Selector<'a> {
+    // An `id` attribute replaces the id the scope would set
+    let id = options.attributes.get("id");
+    match (&options.css_scope, id) {
+        (CssScope::Root, _) => Selector::Root,
+        (CssScope::Diagram, _) => Selector::Diagram,
+        (CssScope::Unique | CssScope::Id(_), Some(id)) => Selector::Id(SvgId::Given(id)),
+        (CssScope::Unique, None) => Selector::Id(SvgId::Unique(unique_id(diagram, options))),
+        (CssScope::Id(id), None) => Selector::Id(SvgId::Given(id)),
+    }
+}
+
+/// Derive a stable id hash from the diagram content and render options.
+/// Identical input renders to identical output, including the id, so two
+/// copies of one diagram on a page share it; [`CssScope::Id`] tells them
+/// apart.
+fn unique_id(diagram: &Diagram, options: &RenderOptions) -> u64 {
+    // FNV-1a, fed through fmt::Write so nothing is buffered
+    struct Fnv(u64);
+    impl Write for Fnv {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            for byte in s.bytes() {
+                self.0 ^= byte as u64;
+                self.0 = self.0.wrapping_mul(0x100000001b3);
+            }
+            Ok(())
+        }
+    }
+
+    let mut hasher = Fnv(0xcbf29ce484222325);
+    let _ = write!(hasher, "{:?}{:?}", diagram, options);
+    hasher.0
+}
+
+/// How colors are chosen and written into the SVG.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorMode {
+    /// CSS variables with a `prefers-color-scheme: dark` override
+    #[default]
+    Adaptive,
+    /// CSS variables set to the light palette only
+    Light,
+    /// CSS variables set to the dark palette only
+    Dark,
+    /// Literal colors from the light or dark palette in the `fill`/`stroke`
+    /// attributes and no `<style>` block, for consumers that ignore CSS
+    /// (xml2rfc, email clients, some PDF converters)
+    Inline(Scheme),
+}
+
+/// One of the two palettes of a [`Theme`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Scheme {
+    /// [`Theme::light`]
+    #[default]
+    Light,
+    /// [`Theme::dark`]
+    Dark,
+}
+
+/// Write the CSS style block for light/dark mode support
+///
+/// `--aasvg-thick-width` is only declared when `thick_width` is given, i.e.
+/// when the diagram has thick lines.
+fn write_css<W: Write>(
+    w: &mut W,
+    options: &RenderOptions,
+    selector: &Selector,
+    thick_width: Option<f64>,
+) -> fmt::Result {
+    let theme = &options.theme;
+    let boxes = options.fill_boxes;
+    w.write_str("<style>\n")?;
+    match options.color_mode {
+        ColorMode::Adaptive => {
+            write_css_rule(w, "  ", selector, &theme.light, thick_width, boxes)?;
+            w.write_str("  @media (prefers-color-scheme: dark) {\n")?;
+            write_css_rule(w, "    ", selector, &theme.dark, None, boxes)?;
+            w.write_str("  }\n")?;
+        }
+        ColorMode::Light => write_css_rule(w, "  ", selector, &theme.light, thick_width, boxes)?,
+        ColorMode::Dark => write_css_rule(w, "  ", selector, &theme.dark, thick_width, boxes)?,
+        ColorMode::Inline(_) => {}
+    }
+    w.write_str("</style>\n")
+}
+
+/// Write one rule setting the `--aasvg-*` variables from a palette
+///
+/// Values that could end the declaration, the rule or the `<style>` element
+/// are left out, see [`is_css_safe`].
+fn write_css_rule<W: Write>(
+    w: &mut W,
+    indent: &str,
+    selector: &Selector,
+    palette: &Palette,
+    thick_width: Option<f64>,
+    boxes: bool,
+) -> fmt::Result {
+    writeln!(w, "{}{} {{", indent, selector)?;
+    let mut declare = |name: &str, value: &str| {
+        if is_css_safe(value) {
+            writeln!(w, "{}  --aasvg-{}: {};", indent, name, value)
+        } else {
+            Ok(())
+        }
+    };
+    declare("stroke", &palette.stroke)?;
+    declare("fill", &palette.fill)?;
+    declare("bg", &palette.bg)?;
+    declare("text", &palette.text)?;
+    for (i, gray) in palette.grays.iter().enumerate() {
+        declare(&format!("gray-{}", i + 1), gray)?;
+    }
+    declare("shade", &palette.shade)?;
+    if boxes {
+        declare("box-fill", &palette.box_fill)?;
+    }
+    if let Some(width) = thick_width {
+        declare("thick-width", &format!("{}px", Coord(width)))?;
+    }
+    writeln!(w, "{}}}", indent)
+}
 
 /// Options for rendering ASCII diagrams to SVG.
 ///
@@ -51,6 +268,67 @@ pub struct RenderOptions {
     /// Stretch text to fit character cells exactly using SVG's
     /// `textLength` and `lengthAdjust` attributes.
     pub stretch: bool,
+    /// Colors for light and dark mode.
+    pub theme: Theme,
+    /// Selector the CSS variables are scoped to.
+    pub css_scope: CssScope,
+    /// Adaptive, fixed light or dark, or inline colors without CSS.
+    pub color_mode: ColorMode,
+    /// Join lines that meet end to end and share a style into one `<path>`
+    /// each, so a box becomes a single closed path. Shrinks the output and
+    /// keeps dash patterns continuous around corners.
+    pub join_paths: bool,
+    /// Fill the boxes found in the diagram with `--aasvg-box-fill`, drawn
+    /// under the lines. See [`Diagram::regions`].
+    pub fill_boxes: bool,
+    /// Accessible name, written as a `<title>` element. With a title or a
+    /// description the SVG gets `role="img"` and an `aria-labelledby`
+    /// pointing at them. Their ids are prefixed with the [`CssScope::Id`]
+    /// if one is set and derived from the content otherwise, so identical
+    /// diagrams inlined into one page share them; set an id to keep the
+    /// references unique.
+    pub title: Option<String>,
+    /// Accessible description, written as a `<desc>` element.
+    pub description: Description,
+    /// Debug overlay: draw the character-cell lattice over the diagram and
+    /// mark where each path starts and ends.
+    pub show_grid: bool,
+    /// Debug overlay: tint the cells the path and decoration finders
+    /// consumed, under the diagram. See [`Diagram::used`].
+    pub show_used: bool,
+    /// Draw every character of the input in its cell, to check how each
+    /// was interpreted.
+    pub source_overlay: SourceOverlay,
+    /// Leave out the `width` and `height` attributes, so the SVG scales to
+    /// fill its container.
+    pub fill_container: bool,
+    /// Width of the viewport in characters instead of the diagram's,
+    /// padding or cropping it on the right.
+    pub width: Option<usize>,
+    /// Height of the viewport in characters instead of the diagram's,
+    /// padding or cropping it at the bottom.
+    pub height: Option<usize>,
+    /// Space added around the viewport on every side, in pixels; negative
+    /// values trim the half-cell border. The trim stops at one cell, and a
+    /// margin that is not finite is ignored.
+    pub margin: f64,
+    /// Attributes of the root `<svg>` element, replacing the defaults of the
+    /// same name (such as `class`, `font-family` or `font-size`) or added
+    /// after them. Values are escaped, and names that fail
+    /// [`is_attribute_name`] are left out. An `id` also replaces the one [`CssScope::Unique`] or
+    /// [`CssScope::Id`] sets, in the selector and the accessibility
+    /// references, and a `class` keeps `diagram` under [`CssScope::Diagram`].
+    pub attributes: BTreeMap<String, String>,
+    /// Width of a character cell in pixels, [`SCALE`] if not set. Set it
+    /// to the advance of the font, e.g. 9.6 for a 0.6em-wide font at 16px.
+    /// Like the aspect and font size, it must be positive and finite, or the
+    /// default is used.
+    pub cell_width: Option<f64>,
+    /// Height of a character cell as a multiple of its width, [`ASPECT`] if
+    /// not set.
+    pub aspect: Option<f64>,
+    /// Font size of the text in pixels, 13 if not set.
+    pub font_size: Option<f64>,
 }
 
 impl RenderOptions {
@@ -60,6 +338,24 @@ impl RenderOptions {
             disable_text: false,
             spaces: 2,
             stretch: false,
+            theme: Theme::default(),
+            css_scope: CssScope::Root,
+            color_mode: ColorMode::Adaptive,
+            join_paths: false,
+            fill_boxes: false,
+            title: None,
+            description: Description::None,
+            show_grid: false,
+            show_used: false,
+            source_overlay: SourceOverlay::None,
+            fill_container: false,
+            width: None,
+            height: None,
+            margin: 0.0,
+            attributes: BTreeMap::new(),
+            cell_width: None,
+            aspect: None,
+            font_size: None,
         }
     }
 
@@ -82,135 +378,605 @@ impl RenderOptions {
         self.stretch = stretch;
         self
     }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn with_css_scope(mut self, css_scope: CssScope) -> Self {
+        self.css_scope = css_scope;
+        self
+    }
+
+    pub fn with_color_mode(mut self, color_mode: ColorMode) -> Self {
+        self.color_mode = color_mode;
+        self
+    }
+
+    pub fn with_join_paths(mut self, join_paths: bool) -> Self {
+        self.join_paths = join_paths;
+        self
+    }
+
+    pub fn with_fill_boxes(mut self, fill_boxes: bool) -> Self {
+        self.fill_boxes = fill_boxes;
+        self
+    }
+
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn with_description(mut self, description: Description) -> Self {
+        self.description = description;
+        self
+    }
+
+    pub fn with_show_grid(mut self, show_grid: bool) -> Self {
+        self.show_grid = show_grid;
+        self
+    }
+
+    pub fn with_show_used(mut self, show_used: bool) -> Self {
+        self.show_used = show_used;
+        self
+    }
+
+    pub fn with_source_overlay(mut self, source_overlay: SourceOverlay) -> Self {
+        self.source_overlay = source_overlay;
+        self
+    }
+
+    pub fn with_fill_container(mut self, fill_container: bool) -> Self {
+        self.fill_container = fill_container;
+        self
+    }
+
+    /// Set the viewport size in characters; `None` keeps the diagram's
+    pub fn with_size(mut self, width: Option<usize>, height: Option<usize>) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn with_margin(mut self, margin: f64) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Set an attribute of the root `<svg>` element; names that fail
+    /// [`is_attribute_name`] are left out
+    pub fn with_attribute(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.attributes.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn with_cell_width(mut self, cell_width: f64) -> Self {
+        self.cell_width = Some(cell_width);
+        self
+    }
+
+    pub fn with_aspect(mut self, aspect: f64) -> Self {
+        self.aspect = Some(aspect);
+        self
+    }
+
+    pub fn with_font_size(mut self, font_size: f64) -> Self {
+        self.font_size = Some(font_size);
+        self
+    }
+
+    /// Cell and font size to lay the geometry out with
+    pub(crate) fn metrics(&self) -> Metrics {
+        // Sizes that would collapse or mirror the image are ignored
+        let valid = |value: Option<f64>| value.filter(|v| v.is_finite() && *v > 0.0);
+        Metrics::new(
+            valid(self.cell_width).unwrap_or(SCALE),
+            valid(self.aspect).unwrap_or(ASPECT),
+            valid(self.font_size).unwrap_or(FONT_SIZE),
+        )
+    }
+
+    /// Palette for outputs without CSS: the dark one for
+    /// [`ColorMode::Dark`] and inline dark colors, the light one otherwise
+    pub(crate) fn fixed_palette(&self) -> &Palette {
+        match self.color_mode {
+            ColorMode::Dark | ColorMode::Inline(Scheme::Dark) => &self.theme.dark,
+            _ => &self.theme.light,
+        }
+    }
+}
+
+/// Visible area of the canvas, in pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Viewport {
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+    pub(crate) width: f64,
+    pub(crate) height: f64,
+}
+
+impl Viewport {
+    /// The diagram's grid, or the cells set in `options`, with a half-cell
+    /// border and `options.margin` around it
+    pub(crate) fn new(diagram: &Diagram, options: &RenderOptions) -> Self {
+        let columns = options.width.unwrap_or(diagram.width);
+        let rows = options.height.unwrap_or(diagram.height);
+        let metrics = options.metrics();
+        let width = (columns + 1) as f64 * metrics.cell_width;
+        let height = (rows + 1) as f64 * metrics.cell_height;
+        let margin = if options.margin.is_finite() {
+            options.margin
+        } else {
+            0.0
+        };
+        // Trim down to one cell at most, so there is always an image
+        let margin_x = margin.max((metrics.cell_width - width) / 2.0);
+        let margin_y = margin.max((metrics.cell_height - height) / 2.0);
+        Self {
+            // Not -margin, which formats as "-0"
+            x: 0.0 - margin_x,
+            y: 0.0 - margin_y,
+            width: width + 2.0 * margin_x,
+            height: height + 2.0 * margin_y,
+        }
+    }
 }
 
 /// Generate complete SVG from paths, decorations, and remaining text
-pub fn generate_svg(
-    grid: &mut Grid,
-    paths: &PathSet,
-    decorations: &DecorationSet,
+pub fn generate_svg(diagram: &Diagram, options: &RenderOptions) -> String {
+    let mut svg = String::new();
+    let _ = write_svg(&mut svg, diagram, options);
+    svg
+}
+
+/// Write complete SVG to `w` without building it in memory first
+pub(crate) fn write_svg<W: Write>(
+    w: &mut W,
+    diagram: &Diagram,
     options: &RenderOptions,
-) -> String {
-    let width = ((grid.width + 1) as f64 * SCALE) as u32;
-    let height = ((grid.height + 1) as f64 * SCALE * ASPECT) as u32;
+) -> fmt::Result {
+    let inline = matches!(options.color_mode, ColorMode::Inline(_));
+    let palette = inline.then(|| options.fixed_palette());
+    write_svg_with(w, diagram, options, palette)
+}
 
+/// Write complete SVG to an `io::Write`, returning the first I/O error
+pub(crate) fn write_svg_io<W: io::Write>(
+    w: &mut W,
+    diagram: &Diagram,
+    options: &RenderOptions,
+) -> io::Result<()> {
+    // fmt::Error carries no details, so keep the io::Error that caused it
+    struct Adapter<'a, W> {
+        inner: &'a mut W,
+        error: Option<io::Error>,
+    }
+    impl<W: io::Write> Write for Adapter<'_, W> {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.inner.write_all(s.as_bytes()).map_err(|e| {
+                self.error = Some(e);
+                fmt::Error
+            })
+        }
+    }
+
+    let mut adapter = Adapter {
+        inner: w,
+        error: None,
+    };
+    write_svg(&mut adapter, diagram, options).map_err(|_| {
+        adapter
+            .error
+            .unwrap_or_else(|| io::Error::new(io::ErrorKind::Other, "formatter error"))
+    })
+}
+
+/// Generate complete SVG, writing literal colors from `palette` instead of
+/// the CSS block when one is given
+#[cfg_attr(not(feature = "png"), allow(dead_code))]
+pub(crate) fn generate_svg_with(
+    diagram: &Diagram,
+    options: &RenderOptions,
+    palette: Option<&Palette>,
+) -> String {
     let mut svg = String::new();
+    let _ = write_svg_with(&mut svg, diagram, options, palette);
+    svg
+}
+
+fn write_svg_with<W: Write>(
+    w: &mut W,
+    diagram: &Diagram,
+    options: &RenderOptions,
+    palette: Option<&Palette>,
+) -> fmt::Result {
+    let viewport = Viewport::new(diagram, options);
+    let metrics = options.metrics();
+    let selector = css_selector(diagram, options);
 
-    // SVG header
-    let _ = write!(
-        svg,
-        r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.1" width="{}" height="{}" viewBox="0 0 {} {}" class="diagram" text-anchor="middle" font-family="monospace" font-size="13px" stroke-linecap="round">
-"#,
-        width, height, width, height
+    // Accessible name and description, referenced by ids prefixed with the
+    // SVG's own so they stay unique on a page
+    let title = options.title.as_deref();
+    let description = description_text(diagram, options);
+    let prefix = (title.is_some() || description.is_some()).then(|| {
+        match (&selector, options.attributes.get("id")) {
+            (Selector::Id(SvgId::Given(id)), _) => Escaped(id).to_string(),
+            (Selector::Id(id), _) => id.to_string(),
+            (_, Some(id)) => Escaped(id).to_string(),
+            _ => SvgId::Unique(unique_id(diagram, options)).to_string(),
+        }
+    });
+
+    // SVG header, with values already escaped
+    let mut root: Vec<(&str, Cow<str>)> = vec![
+        ("xmlns", "http://www.w3.org/2000/svg".into()),
+        ("version", "1.1".into()),
+    ];
+    match selector {
+        Selector::Id(SvgId::Given(id)) => root.push(("id", Escaped(id).to_string().into())),
+        Selector::Id(id) => root.push(("id", id.to_string().into())),
+        _ => {}
+    }
+    if !options.fill_container {
+        root.push(("width", Coord(viewport.width).to_string().into()));
+        root.push(("height", Coord(viewport.height).to_string().into()));
+    }
+    let font_size = format!("{}px", Coord(metrics.font_size));
+    let view_box = format!(
+        "{} {} {} {}",
+        Coord(viewport.x),
+        Coord(viewport.y),
+        Coord(viewport.width),
+        Coord(viewport.height)
     );
+    root.extend([
+        ("viewBox", view_box.into()),
+        ("class", "diagram".into()),
+        ("text-anchor", "middle".into()),
+        ("font-family", "monospace".into()),
+        ("font-size", font_size.into()),
+        ("stroke-linecap", "round".into()),
+    ]);
+    // Round joins look like the round caps of separate lines
+    if options.join_paths {
+        root.push(("stroke-linejoin", "round".into()));
+    }
+    if let Some(prefix) = &prefix {
+        let mut labels = Vec::new();
+        if title.is_some() {
+            labels.push(format!("{}-title", prefix));
+        }
+        if description.is_some() {
+            labels.push(format!("{}-desc", prefix));
+        }
+        root.push(("role", "img".into()));
+        root.push(("aria-labelledby", labels.join(" ").into()));
+    }
 
-    // CSS variables for light/dark mode
-    svg.push_str(CSS_VARIABLES);
+    w.write_str("<svg")?;
+    for (name, value) in &root {
+        match options.attributes.get(*name) {
+            // `svg.diagram` needs the class the value replaces
+            Some(value)
+                if *name == "class"
+                    && matches!(selector, Selector::Diagram)
+                    && !value.split_whitespace().any(|class| class == "diagram") =>
+            {
+                write!(w, " class=\"diagram {}\"", Escaped(value))?
+            }
+            Some(value) => write!(w, " {}=\"{}\"", name, Escaped(value))?,
+            None => write!(w, " {}=\"{}\"", name, value)?,
+        }
+    }
+    for (name, value) in &options.attributes {
+        if is_attribute_name(name) && !root.iter().any(|(root_name, _)| root_name == name) {
+            write!(w, " {}=\"{}\"", name, Escaped(value))?;
+        }
+    }
+    w.write_str(">\n")?;
+    if let Some(prefix) = &prefix {
+        if let Some(title) = title {
+            writeln!(
+                w,
+                "<title id=\"{}-title\">{}</title>",
+                prefix,
+                Escaped(title)
+            )?;
+        }
+        if let Some(description) = &description {
+            writeln!(
+                w,
+                "<desc id=\"{}-desc\">{}</desc>",
+                prefix,
+                Escaped(description)
+            )?;
+        }
+    }
+
+    // CSS variables for light/dark mode, or literal colors without CSS
+    let colors = match palette {
+        Some(palette) => Colors::Literal(palette),
+        None => {
+            let thick = diagram.paths.iter().any(|path| path.style.thick);
+            write_css(w, options, &selector, thick.then(|| metrics.thick_width()))?;
+            Colors::Variables
+        }
+    };
 
     // Backdrop
     if options.backdrop {
-        let _ = write!(
-            svg,
-            r#"<rect x="0" y="0" width="{}" height="{}" fill="var(--aasvg-bg)"/>
-"#,
-            width, height
-        );
+        writeln!(
+            w,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#,
+            Coord(viewport.x),
+            Coord(viewport.y),
+            Coord(viewport.width),
+            Coord(viewport.height),
+            colors.bg()
+        )?;
+    }
+
+    // Consumed cells, under everything else
+    if options.show_used {
+        write_used_cells(w, diagram, &metrics)?;
+    }
+
+    if options.source_overlay == SourceOverlay::Under {
+        write_source(w, diagram, colors, &metrics)?;
+    }
+
+    // Box fills, under the lines
+    if options.fill_boxes && !diagram.regions.is_empty() {
+        writeln!(w, "<g fill=\"{}\">", colors.box_fill())?;
+        for region in &diagram.regions {
+            region.write_svg(w, &metrics)?;
+        }
+        w.write_str("</g>\n")?;
     }
 
     // Paths
-    svg.push_str(&paths.to_svg());
+    diagram
+        .paths
+        .write_svg(w, colors, options.join_paths, &metrics)?;
 
     // Decorations
-    svg.push_str(&decorations.to_svg());
+    diagram.decorations.write_svg(w, colors, &metrics)?;
 
     // Text
     if !options.disable_text {
-        svg.push_str(&extract_text(grid, options.spaces, options.stretch));
+        write_text(w, &diagram.text, options.stretch, colors, &metrics)?;
+    }
+
+    if options.source_overlay == SourceOverlay::Over {
+        write_source(w, diagram, colors, &metrics)?;
+    }
+
+    // Cell lattice and path ends, on top
+    if options.show_grid {
+        write_grid(w, diagram, colors, &metrics)?;
     }
 
     // Close SVG
-    svg.push_str("</svg>");
+    w.write_str("</svg>")
+}
 
-    svg
+/// Text for the `<desc>` element, if there is any
+fn description_text<'a>(diagram: &Diagram, options: &'a RenderOptions) -> Option<Cow<'a, str>> {
+    match &options.description {
+        Description::None => None,
+        Description::Text(text) => Some(Cow::Borrowed(text)),
+        Description::Auto => {
+            let mut text = String::new();
+            let words = diagram
+                .text
+                .iter()
+                .filter(|run| run.text.chars().any(char::is_alphanumeric));
+            for run in words {
+                if !text.is_empty() {
+                    text.push_str(", ");
+                }
+                text.push_str(&run.text);
+            }
+            (!text.is_empty()).then_some(Cow::Owned(text))
+        }
+    }
+}
+
+/// Write SVG text elements for the remaining text runs
+fn write_text<W: Write>(
+    w: &mut W,
+    runs: &[TextRun],
+    stretch: bool,
+    colors: Colors,
+    metrics: &Metrics,
+) -> fmt::Result {
+    writeln!(w, "<g fill=\"{}\">", colors.text())?;
+
+    for run in runs {
+        let Vec2 { x: px, y: py } = run.anchor(metrics);
+        let escaped = Escaped(&run.text);
+
+        if stretch {
+            let text_length = run.len() as f64 * metrics.cell_width;
+            writeln!(
+                w,
+                "<text x=\"{}\" y=\"{}\" textLength=\"{}\" lengthAdjust=\"spacingAndGlyphs\">{}</text>",
+                Coord(px), Coord(py), Coord(text_length), escaped
+            )?;
+        } else {
+            writeln!(
+                w,
+                "<text x=\"{}\" y=\"{}\">{}</text>",
+                Coord(px),
+                Coord(py),
+                escaped
+            )?;
+        }
+    }
+
+    w.write_str("</g>\n")
 }
 
-/// Extract remaining text from the grid and generate SVG text elements
-fn extract_text(grid: &mut Grid, spaces: u32, stretch: bool) -> String {
-    let mut result = String::new();
-    result.push_str("<g fill=\"var(--aasvg-text)\">\n");
+/// Write each non-blank input character as faint text centered in its cell
+fn write_source<W: Write>(
+    w: &mut W,
+    diagram: &Diagram,
+    colors: Colors,
+    metrics: &Metrics,
+) -> fmt::Result {
+    writeln!(
+        w,
+        "<g class=\"aasvg-source\" fill=\"{}\" fill-opacity=\"0.4\">",
+        colors.text()
+    )?;
+    let mut buf = [0; 4];
+    for (y, row) in diagram.source.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            if c.is_whitespace() {
+                continue;
+            }
+            // Same baseline as the text runs
+            let center = metrics.point(Vec2::from_grid(x as i32, y as i32));
+            writeln!(
+                w,
+                "<text x=\"{}\" y=\"{}\">{}</text>",
+                Coord(center.x),
+                Coord(center.y + metrics.baseline()),
+                Escaped(c.encode_utf8(&mut buf))
+            )?;
+        }
+    }
+    w.write_str("</g>\n")
+}
+
+/// Color of the debug overlays, which stands out in light and dark mode
+const OVERLAY_COLOR: &str = "#e5484d";
 
-    for y in 0..grid.height as i32 {
+/// Tint the cells the finders consumed, one `<rect>` per run of cells
+fn write_used_cells<W: Write>(w: &mut W, diagram: &Diagram, metrics: &Metrics) -> fmt::Result {
+    let (cell_width, cell_height) = (metrics.cell_width, metrics.cell_height);
+    writeln!(
+        w,
+        "<g class=\"aasvg-used\" fill=\"{}\" fill-opacity=\"0.2\">",
+        OVERLAY_COLOR
+    )?;
+    for (y, row) in diagram.used.chunks(diagram.width.max(1)).enumerate() {
         let mut x = 0;
-        while x < grid.width as i32 {
-            if let Some(start_x) = grid.text_start(x, y, spaces) {
-                let text = grid.extract_text(start_x, y, spaces);
-                if !text.is_empty() {
-                    // Restore hidden markers (o, v, V that were part of text)
-                    let text = unhide_markers(&text);
-                    let char_count = text.chars().count();
-                    let px = (start_x as f64 + 1.0 + (char_count as f64 - 1.0) / 2.0) * SCALE;
-                    let py = (y as f64 + 1.0) * SCALE * ASPECT + 4.0;
-
-                    let escaped = escape_xml(&text);
-
-                    if stretch {
-                        let text_length = char_count as f64 * SCALE;
-                        let _ = write!(
-                            result,
-                            "<text x=\"{}\" y=\"{}\" textLength=\"{}\" lengthAdjust=\"spacingAndGlyphs\">{}</text>\n",
-                            px, py, text_length, escaped
-                        );
-                    } else {
-                        let _ = write!(
-                            result,
-                            "<text x=\"{}\" y=\"{}\">{}</text>\n",
-                            px, py, escaped
-                        );
-                    }
-                }
-                x = start_x + text.chars().count() as i32;
-            } else {
-                break;
+        while x < row.len() {
+            if !row[x] {
+                x += 1;
+                continue;
             }
+            let start = x;
+            while x < row.len() && row[x] {
+                x += 1;
+            }
+            writeln!(
+                w,
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>",
+                Coord((start as f64 + 0.5) * cell_width),
+                Coord((y as f64 + 0.5) * cell_height),
+                Coord((x - start) as f64 * cell_width),
+                Coord(cell_height)
+            )?;
+        }
+    }
+    w.write_str("</g>\n")
+}
+
+/// Draw the cell boundaries and a dot at both ends of every path
+fn write_grid<W: Write>(
+    w: &mut W,
+    diagram: &Diagram,
+    colors: Colors,
+    metrics: &Metrics,
+) -> fmt::Result {
+    let (cell_width, cell_height) = (metrics.cell_width, metrics.cell_height);
+    let (width, height) = (diagram.width as f64, diagram.height as f64);
+    let (left, right) = (0.5 * cell_width, (width + 0.5) * cell_width);
+    let (top, bottom) = (0.5 * cell_height, (height + 0.5) * cell_height);
+
+    writeln!(
+        w,
+        "<g class=\"aasvg-grid\" fill=\"none\" stroke=\"{}\" stroke-opacity=\"0.25\" stroke-width=\"0.5\">",
+        colors.stroke()
+    )?;
+    w.write_str("<path d=\"")?;
+    for x in 0..=diagram.width {
+        let x = Coord((x as f64 + 0.5) * cell_width);
+        write!(w, "M{} {}V{}", x, Coord(top), Coord(bottom))?;
+    }
+    for y in 0..=diagram.height {
+        let y = Coord((y as f64 + 0.5) * cell_height);
+        write!(w, "M{} {}H{}", Coord(left), y, Coord(right))?;
+    }
+    w.write_str("\"/>\n</g>\n")?;
+
+    writeln!(w, "<g class=\"aasvg-ends\" fill=\"{}\">", OVERLAY_COLOR)?;
+    for path in diagram.paths.iter() {
+        for end in [path.a, path.b].map(|p| metrics.point(p)) {
+            writeln!(
+                w,
+                "<circle cx=\"{}\" cy=\"{}\" r=\"1.5\"/>",
+                Coord(end.x),
+                Coord(end.y)
+            )?;
         }
     }
+    w.write_str("</g>\n")
+}
 
-    result.push_str("</g>\n");
-    result
+/// Whether `name` can be written as an attribute name: a letter, `_` or `:`
+/// followed by letters, digits, `-`, `.`, `_` or `:`.
+///
+/// Attributes set with [`RenderOptions::with_attribute`] whose names fail
+/// this check are left out of the SVG.
+///
+/// ```rust
+/// assert!(aasvg::is_attribute_name("data-note"));
+/// assert!(!aasvg::is_attribute_name("a b"));
+/// ```
+pub fn is_attribute_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_' || c == ':')
+        && chars.all(|c| c.is_alphanumeric() || matches!(c, '-' | '.' | '_' | ':'))
 }
 
-/// Escape special XML characters
-fn escape_xml(s: &str) -> String {
-    let mut result = String::with_capacity(s.len());
-    for c in s.chars() {
-        match c {
-            '&' => result.push_str("&amp;"),
-            '<' => result.push_str("&lt;"),
-            '>' => result.push_str("&gt;"),
-            '"' => result.push_str("&quot;"),
-            '\'' => result.push_str("&#39;"),
-            _ => result.push(c),
+/// Text with special XML characters escaped when displayed
+pub(crate) struct Escaped<'a>(pub(crate) &'a str);
+
+impl fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut rest = self.0;
+        while let Some(i) = rest.find(['&', '<', '>', '"', '\'']) {
+            f.write_str(&rest[..i])?;
+            f.write_str(match rest.as_bytes()[i] {
+                b'&' => "&amp;",
+                b'<' => "&lt;",
+                b'>' => "&gt;",
+                b'"' => "&quot;",
+                _ => "&#39;",
+            })?;
+            rest = &rest[i + 1..];
         }
+        f.write_str(rest)
     }
-    result
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::finder::{find_decorations, find_paths};
 
     #[test]
     fn test_svg_generation() {
-        let mut grid = Grid::new("+--+\n|  |\n+--+");
-        let mut paths = PathSet::new();
-        let mut decorations = DecorationSet::new();
-
-        find_paths(&mut grid, &mut paths);
-        find_decorations(&mut grid, &paths, &mut decorations);
-
         let options = RenderOptions::new();
-        let svg = generate_svg(&mut grid, &paths, &decorations, &options);
+        let diagram = Diagram::parse("+--+\n|  |\n+--+", &options);
+        let svg = generate_svg(&diagram, &options);
 
         assert!(svg.starts_with("<svg"));
         assert!(svg.ends_with("</svg>"));
@@ -220,22 +986,348 @@ mod tests {
 
     #[test]
     fn test_svg_with_backdrop() {
-        let mut grid = Grid::new("--");
-        let mut paths = PathSet::new();
-        let decorations = DecorationSet::new();
+        let options = RenderOptions::new().with_backdrop(true);
+        let diagram = Diagram::parse("--", &options);
+        let svg = generate_svg(&diagram, &options);
+
+        assert!(svg.contains(r#"fill="var(--aasvg-bg)"#));
+    }
+
+    #[test]
+    fn test_svg_theme() {
+        let options = RenderOptions::new().with_theme(Theme::high_contrast());
+        let diagram = Diagram::parse("--", &options);
+        let svg = generate_svg(&diagram, &options);
+
+        assert!(svg.contains("--aasvg-bg: #fff;"));
+        assert!(svg.contains("--aasvg-bg: #000;"));
+        assert!(!svg.contains("#1a1a1a"));
+    }
+
+    #[test]
+    fn test_svg_theme_unsafe_values() {
+        let mut theme = Theme::classic();
+        theme.light.stroke = "red;}</style><script>alert(1)</script>".to_string();
+        theme.dark.fill = "red /* }".to_string();
+        let options = RenderOptions::new().with_theme(theme);
+        let diagram = Diagram::parse("--", &options);
+        let svg = generate_svg(&diagram, &options);
+
+        assert!(!svg.contains("<script>"));
+        assert!(!svg.contains("/*"));
+        assert_eq!(svg.matches("--aasvg-stroke:").count(), 1);
+        assert_eq!(svg.matches("--aasvg-fill:").count(), 1);
+    }
+
+    #[test]
+    fn test_svg_css_scope() {
+        let options = RenderOptions::new().with_css_scope(CssScope::Diagram);
+        let diagram = Diagram::parse("--", &options);
+        let svg = generate_svg(&diagram, &options);
+        assert!(svg.contains("  svg.diagram {"));
+        assert!(!svg.contains(":root"));
+
+        let options = RenderOptions::new().with_css_scope(CssScope::Id("fig-1".to_string()));
+        let svg = generate_svg(&diagram, &options);
+        assert!(svg.contains(r#" id="fig-1""#));
+        assert!(svg.contains("  svg#fig-1 {"));
+        assert!(svg.contains("    svg#fig-1 {"));
+    }
+
+    #[test]
+    fn test_svg_unique_scope() {
+        let options = RenderOptions::new().with_css_scope(CssScope::Unique);
+        let a = generate_svg(&Diagram::parse("--", &options), &options);
+        let b = generate_svg(&Diagram::parse("---", &options), &options);
+        let dark = options.clone().with_theme(Theme::high_contrast());
+        let c = generate_svg(&Diagram::parse("--", &dark), &dark);
+
+        let id = |svg: &str| svg.split('"').nth(5).unwrap().to_string();
+        assert!(id(&a).starts_with("aasvg-"));
+        assert!(a.contains(&format!("svg#{} {{", id(&a))));
+        assert_ne!(id(&a), id(&b));
+        assert_ne!(id(&a), id(&c));
+        // Rendering is deterministic
+        assert_eq!(a, generate_svg(&Diagram::parse("--", &options), &options));
+    }
+
+    #[test]
+    fn test_svg_color_modes() {
+        let options = RenderOptions::new().with_theme(Theme::github());
+        let diagram = Diagram::parse("o-->", &options);
+
+        let light = generate_svg(&diagram, &options.clone().with_color_mode(ColorMode::Light));
+        assert!(light.contains("--aasvg-stroke: #1f2328;"));
+        assert!(!light.contains("#e6edf3"));
+        assert!(!light.contains("prefers-color-scheme"));
+
+        let dark = generate_svg(&diagram, &options.clone().with_color_mode(ColorMode::Dark));
+        assert!(dark.contains("--aasvg-stroke: #e6edf3;"));
+        assert!(!dark.contains("#1f2328"));
+        assert!(!dark.contains("prefers-color-scheme"));
+
+        let inline = options
+            .with_color_mode(ColorMode::Inline(Scheme::Light))
+            .with_backdrop(true);
+        let svg = generate_svg(&diagram, &inline);
+        assert!(!svg.contains("<style"));
+        assert!(!svg.contains("var("));
+        assert!(svg.contains(r##"stroke="#1f2328""##));
+        assert!(svg.contains(r##"fill="#ffffff""##));
+
+        let inline = inline.with_color_mode(ColorMode::Inline(Scheme::Dark));
+        let svg = generate_svg(&diagram, &inline);
+        assert!(!svg.contains("<style"));
+        assert!(svg.contains(r##"stroke="#e6edf3""##));
+        assert!(svg.contains(r##"fill="#0d1117""##));
+        assert!(!svg.contains("#1f2328"));
+
+        // Derived colors are written as colors the consumer can paint
+        let brand = Theme::new(
+            Palette::new("#000", "#333", "#fff", "#000"),
+            Palette::new("#fff", "#ccc", "#000", "#fff"),
+        );
+        let inline = RenderOptions::new()
+            .with_theme(brand)
+            .with_color_mode(ColorMode::Inline(Scheme::Light))
+            .with_fill_boxes(true);
+        let svg = generate_svg(&Diagram::parse("+--+\n|  |\n+--+", &inline), &inline);
+        assert!(!svg.contains("color-mix"));
+        assert!(svg.contains(r##"<g fill="#ebebeb">"##));
+
+        // Literal colors cannot end the attribute they are written into
+        let mut theme = Theme::github();
+        theme.light.stroke = r#"red" onload="alert(1)"#.to_string();
+        let inline = RenderOptions::new()
+            .with_theme(theme)
+            .with_color_mode(ColorMode::Inline(Scheme::Light));
+        let svg = generate_svg(&diagram, &inline);
+        assert!(!svg.contains(r#"" onload=""#));
+        assert!(svg.contains(r#"stroke="red&quot; onload=&quot;alert(1)""#));
+    }
+
+    #[test]
+    fn test_svg_accessibility() {
+        let options = RenderOptions::new();
+        let diagram = Diagram::parse("+------+\n| DB & |\n+------+  -->  ok", &options);
+        assert!(!generate_svg(&diagram, &options).contains("role="));
+
+        let options = RenderOptions::new()
+            .with_css_scope(CssScope::Id("fig-2".to_string()))
+            .with_title("Storage")
+            .with_description(Description::Auto);
+        let svg = generate_svg(&diagram, &options);
+        assert!(svg.contains(r#" role="img" aria-labelledby="fig-2-title fig-2-desc">"#));
+        let body = svg.split_once(">\n").unwrap().1;
+        assert!(body.starts_with(
+            "<title id=\"fig-2-title\">Storage</title>\n<desc id=\"fig-2-desc\">DB &amp;, ok</desc>\n"
+        ));
+
+        // Without an id the references are derived from the content
+        let options = RenderOptions::new().with_description(Description::Text("Flow".into()));
+        let svg = generate_svg(&diagram, &options);
+        let id = unique_id(&diagram, &options);
+        assert!(svg.contains(&format!(r#"aria-labelledby="aasvg-{:016x}-desc""#, id)));
+        assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.1" width"#));
+        assert!(!svg.contains("<title"));
+
+        // Nothing to describe
+        let options = RenderOptions::new().with_description(Description::Auto);
+        let svg = generate_svg(&Diagram::parse("-->  |", &options), &options);
+        assert!(!svg.contains("<desc") && !svg.contains("role="));
+    }
+
+    #[test]
+    fn test_svg_debug_overlays() {
+        let options = RenderOptions::new();
+        let diagram = Diagram::parse("--> ab\n|\n|", &options);
+        assert!(diagram.is_used(0, 0) && diagram.is_used(2, 0) && diagram.is_used(0, 2));
+        assert!(!diagram.is_used(1, 1) && !diagram.is_used(4, 0) && !diagram.is_used(9, 9));
+        let svg = generate_svg(&diagram, &options);
+        assert!(!svg.contains("aasvg-grid") && !svg.contains("aasvg-used"));
+
+        let options = RenderOptions::new()
+            .with_show_grid(true)
+            .with_show_used(true);
+        let svg = generate_svg(&diagram, &options);
+        // Runs of used cells become one rect each
+        let used = svg.split_once("<g class=\"aasvg-used\"").unwrap().1;
+        let used = &used[..used.find("</g>").unwrap()];
+        assert_eq!(used.matches("<rect").count(), 3);
+        assert!(used.contains(r#"<rect x="4" y="8" width="24" height="16"/>"#));
+        // Cell boundaries halfway between the cell centers
+        assert!(svg.contains(r#"<path d="M4 8V56M12 8V56"#));
+        assert!(svg.contains("M4 56H52\"/>"));
+        assert_eq!(svg.matches("<circle").count(), 4);
+        assert!(svg.find("aasvg-used").unwrap() < svg.find("<path").unwrap());
+        assert!(svg.find("aasvg-grid").unwrap() > svg.find("<text").unwrap());
+    }
+
+    #[test]
+    fn test_svg_source_overlay() {
+        let options = RenderOptions::new();
+        let diagram = Diagram::parse("  +-> <o\n  | so", &options);
+        assert_eq!(diagram.source, vec!["+-> <o", "| so"]);
 
-        find_paths(&mut grid, &mut paths);
+        let under = options.clone().with_source_overlay(SourceOverlay::Under);
+        let svg = generate_svg(&diagram, &under);
+        let group = svg
+            .find(r#"<g class="aasvg-source" fill="var(--aasvg-text)""#)
+            .unwrap();
+        assert!(group < svg.find("<path").unwrap());
+        let overlay = &svg[group..];
+        let overlay = &overlay[..overlay.find("</g>").unwrap()];
+        assert_eq!(overlay.matches("<text").count(), 8);
+        assert!(overlay.contains(r#"<text x="40" y="20">&lt;</text>"#));
+        // Hidden markers show as typed
+        assert!(overlay.contains(r#"<text x="32" y="36">o</text>"#));
 
+        let over = options.with_source_overlay(SourceOverlay::Over);
+        let svg = generate_svg(&diagram, &over);
+        assert!(svg.find("aasvg-source").unwrap() > svg.find("<text").unwrap());
+    }
+
+    #[test]
+    fn test_svg_viewport() {
         let options = RenderOptions::new().with_backdrop(true);
-        let svg = generate_svg(&mut grid, &paths, &decorations, &options);
+        let diagram = Diagram::parse("+--+\n|  |\n+--+", &options);
+        let header = |options: &RenderOptions| {
+            let svg = generate_svg(&diagram, options);
+            let end = svg.find(" class=").unwrap();
+            svg[..end].to_string()
+        };
+        assert!(header(&options).ends_with(r#" width="40" height="64" viewBox="0 0 40 64""#));
 
-        assert!(svg.contains(r#"fill="var(--aasvg-bg)"#));
+        let fill = options.clone().with_fill_container(true);
+        assert!(header(&fill).ends_with(r#" version="1.1" viewBox="0 0 40 64""#));
+
+        // Padded to 8x4 cells, then cropped back by the margin
+        let sized = options.with_size(Some(8), Some(4)).with_margin(-2.5);
+        assert!(header(&sized).ends_with(r#" width="67" height="75" viewBox="2.5 2.5 67 75""#));
+        let svg = generate_svg(&diagram, &sized);
+        let backdrop = r#"<rect x="2.5" y="2.5" width="67" height="75" fill="var(--aasvg-bg)"/>"#;
+        assert!(svg.contains(backdrop));
+
+        // Trimming stops at the middle cell
+        let trimmed = RenderOptions::new().with_margin(-100.0);
+        assert!(header(&trimmed).ends_with(r#" width="8" height="16" viewBox="16 24 8 16""#));
+        let nan = RenderOptions::new().with_margin(f64::NAN);
+        assert_eq!(header(&nan), header(&RenderOptions::new()));
+    }
+
+    #[test]
+    fn test_svg_cell_metrics() {
+        let options = RenderOptions::new()
+            .with_cell_width(10.0)
+            .with_aspect(2.5)
+            .with_font_size(20.0)
+            .with_stretch(true);
+        let diagram = Diagram::parse("+--+\n|Hi|-->\n+--+", &options);
+        let svg = generate_svg(&diagram, &options);
+        assert!(svg.contains(r#" width="80" height="100" viewBox="0 0 80 100""#));
+        assert!(svg.contains(r#" font-size="20px""#));
+        assert!(svg.contains(r#"<path d="M 10,25 L 40,25""#));
+        assert!(svg.contains(
+            r#"<polygon points="80,50 65,41.25 65,58.75" fill="var(--aasvg-fill)" transform="rotate(0,70,50)"/>"#
+        ));
+        // Baseline 4px below the center at 13px, so 20 * 4 / 13 at 20px
+        assert!(svg.contains(
+            r#"<text x="25" y="56.15385" textLength="20" lengthAdjust="spacingAndGlyphs">Hi</text>"#
+        ));
+
+        // Sizes that cannot be drawn fall back to the defaults
+        let default = generate_svg(&diagram, &RenderOptions::new().with_stretch(true));
+        for invalid in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let options = RenderOptions::new()
+                .with_cell_width(invalid)
+                .with_aspect(invalid)
+                .with_font_size(invalid)
+                .with_stretch(true);
+            assert_eq!(generate_svg(&diagram, &options), default);
+        }
+    }
+
+    #[test]
+    fn test_svg_root_attributes() {
+        let options = RenderOptions::new()
+            .with_css_scope(CssScope::Id("fig".to_string()))
+            .with_attribute("font-size", "16px")
+            .with_attribute("style", "max-width: 100%")
+            .with_attribute("data-note", "a \"b\"");
+        let diagram = Diagram::parse("-->", &options);
+        let svg = generate_svg(&diagram, &options);
+        let header = svg.lines().next().unwrap();
+        assert_eq!(
+            header,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.1" id="fig" width="32" height="32" viewBox="0 0 32 32" class="diagram" text-anchor="middle" font-family="monospace" font-size="16px" stroke-linecap="round" data-note="a &quot;b&quot;" style="max-width: 100%">"#
+        );
+    }
+
+    #[test]
+    fn test_svg_root_attributes_scope() {
+        let diagram = Diagram::parse("-->", &RenderOptions::new());
+
+        // The CSS and the accessibility references follow a replaced id
+        let options = RenderOptions::new()
+            .with_css_scope(CssScope::Unique)
+            .with_title("Flow")
+            .with_attribute("id", "fig");
+        let svg = generate_svg(&diagram, &options);
+        assert!(svg.contains(r#" id="fig""#));
+        assert!(svg.contains(r#" aria-labelledby="fig-title""#));
+        assert!(svg.contains("<title id=\"fig-title\">Flow</title>"));
+        assert!(svg.contains("  svg#fig {"));
+        assert!(!svg.contains("svg#aasvg-") && !svg.contains("\"aasvg-"));
+
+        let options = RenderOptions::new()
+            .with_css_scope(CssScope::Id("fig-1".to_string()))
+            .with_attribute("id", "1 <b>");
+        let svg = generate_svg(&diagram, &options);
+        assert!(svg.contains(r#" id="1 &lt;b&gt;""#));
+        assert!(svg.contains("  svg#\\31 \\20 \\3c b\\3e  {"));
+        assert!(!svg.contains("fig-1"));
+
+        // `svg.diagram` still matches with a replaced class
+        let options = RenderOptions::new()
+            .with_css_scope(CssScope::Diagram)
+            .with_attribute("class", "figure");
+        let svg = generate_svg(&diagram, &options);
+        assert!(svg.contains(r#" class="diagram figure""#));
+        assert!(svg.contains("  svg.diagram {"));
+        let options = options.with_attribute("class", "figure diagram");
+        let svg = generate_svg(&diagram, &options);
+        assert!(svg.contains(r#" class="figure diagram""#));
+        let options = options
+            .with_css_scope(CssScope::Root)
+            .with_attribute("class", "figure");
+        let svg = generate_svg(&diagram, &options);
+        assert!(svg.contains(r#" class="figure""#));
+    }
+
+    #[test]
+    fn test_svg_root_attribute_names() {
+        assert!(is_attribute_name("data-note"));
+        assert!(is_attribute_name("xml:lang"));
+        assert!(!is_attribute_name(""));
+        assert!(!is_attribute_name("a b"));
+        assert!(!is_attribute_name("a\"b"));
+        assert!(!is_attribute_name("1a"));
+
+        let options = RenderOptions::new()
+            .with_attribute("a b", "1")
+            .with_attribute("", "x")
+            .with_attribute("data-ok", "2");
+        let svg = generate_svg(&Diagram::parse("-->", &options), &options);
+        let header = svg.lines().next().unwrap();
+        assert!(header.ends_with(r#" stroke-linecap="round" data-ok="2">"#));
     }
 
     #[test]
     fn test_escape_xml() {
+        let escape_xml = |s: &str| Escaped(s).to_string();
         assert_eq!(escape_xml("a<b>c"), "a&lt;b&gt;c");
         assert_eq!(escape_xml("a&b"), "a&amp;b");
         assert_eq!(escape_xml("\"test\""), "&quot;test&quot;");
+        assert_eq!(escape_xml("it's"), "it&#39;s");
     }
 }
@@ -0,0 +1,390 @@
+//! Color themes for the `--aasvg-*` CSS variables.
+
+use crate::svg::Escaped;
+
+/// Colors for one color scheme.
+///
+/// Each value is written as is into the generated CSS, so any CSS color is
+/// accepted (`#0969da`, `rgb(9 105 218)`, `currentColor`, …). Values that
+/// contain `<`, `>`, `&`, `;`, `{`, `}` or a comment are not valid colors and
+/// are left out of the CSS.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    /// Line color (`--aasvg-stroke`)
+    pub stroke: String,
+    /// Arrow head and filled point color (`--aasvg-fill`)
+    pub fill: String,
+    /// Background color for the backdrop and open points (`--aasvg-bg`)
+    pub bg: String,
+    /// Text color (`--aasvg-text`)
+    pub text: String,
+    /// Gray fills for `▁`, `▂`, `▃` and `█`, from least to most ink
+    /// (`--aasvg-gray-1` to `--aasvg-gray-4`)
+    pub grays: [String; 4],
+    /// Fill color of shaded points `◍` (`--aasvg-shade`)
+    pub shade: String,
+    /// Tint of closed boxes when [`RenderOptions::fill_boxes`] is set
+    /// (`--aasvg-box-fill`)
+    ///
+    /// [`RenderOptions::fill_boxes`]: crate::RenderOptions::fill_boxes
+    pub box_fill: String,
+}
+
+impl Palette {
+    /// Create a palette; grays, the shade and the box fill are mixed from
+    /// `fill` and `bg`, into hex colors when both are hex, `rgb()`, `black`
+    /// or `white` and with `color-mix()` otherwise. Use
+    /// [`Palette::with_grays`], [`Palette::with_shade`] and
+    /// [`Palette::with_box_fill`] to set literal colors instead.
+    pub fn new(
+        stroke: impl Into<String>,
+        fill: impl Into<String>,
+        bg: impl Into<String>,
+        text: impl Into<String>,
+    ) -> Self {
+        let fill = fill.into();
+        let bg = bg.into();
+        // Mixed up front where possible, so outputs without CSS get colors
+        // they can paint
+        let rgb = parse_color(&fill).zip(parse_color(&bg));
+        let mix = |percent: u8| match rgb {
+            Some((a, b)) => {
+                let p = f32::from(percent) / 100.0;
+                to_hex([0, 1, 2].map(|i| a[i] * p + b[i] * (1.0 - p)))
+            }
+            None => format!("color-mix(in srgb, {} {}%, {})", fill, percent, bg),
+        };
+        Self {
+            stroke: stroke.into(),
+            grays: [mix(25), mix(50), mix(75), fill.clone()],
+            shade: mix(60),
+            box_fill: mix(10),
+            fill,
+            bg,
+            text: text.into(),
+        }
+    }
+
+    pub fn with_grays(mut self, grays: [&str; 4]) -> Self {
+        self.grays = grays.map(String::from);
+        self
+    }
+
+    pub fn with_shade(mut self, shade: impl Into<String>) -> Self {
+        self.shade = shade.into();
+        self
+    }
+
+    pub fn with_box_fill(mut self, box_fill: impl Into<String>) -> Self {
+        self.box_fill = box_fill.into();
+        self
+    }
+}
+
+/// Light and dark palettes for the generated SVG.
+///
+/// The light palette is the default; the dark palette applies under
+/// `prefers-color-scheme: dark`.
+///
+/// # Example
+///
+/// ```rust
+/// use aasvg::{render_with_options, Palette, RenderOptions, Theme};
+///
+/// let brand = Theme::new(
+///     Palette::new("#3b2f80", "#3b2f80", "#fff", "#222"),
+///     Palette::new("#b7acff", "#b7acff", "#15122b", "#eee"),
+/// );
+/// let svg = render_with_options("-->", &RenderOptions::new().with_theme(brand));
+/// assert!(svg.contains("--aasvg-stroke: #3b2f80;"));
+///
+/// let svg = render_with_options("-->", &RenderOptions::new().with_theme(Theme::github()));
+/// assert!(svg.contains("--aasvg-bg: #0d1117;"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    /// Colors for light mode
+    pub light: Palette,
+    /// Colors for `prefers-color-scheme: dark`
+    pub dark: Palette,
+}
+
+impl Theme {
+    pub fn new(light: Palette, dark: Palette) -> Self {
+        Self { light, dark }
+    }
+
+    /// Black on white, white on near-black in dark mode
+    pub fn classic() -> Self {
+        Self {
+            light: Palette::new("#000", "#000", "#fff", "#000")
+                .with_grays(["#bfbfbf", "#808080", "#404040", "#000"])
+                .with_shade("#666")
+                .with_box_fill("#e6e6e6"),
+            dark: Palette::new("#fff", "#fff", "#1a1a1a", "#fff")
+                .with_grays(["#535353", "#8c8c8c", "#c6c6c6", "#fff"])
+                .with_shade("#a3a3a3")
+                .with_box_fill("#313131"),
+        }
+    }
+
+    /// Colors matching GitHub's light and dark default themes
+    pub fn github() -> Self {
+        Self {
+            light: Palette::new("#1f2328", "#1f2328", "#ffffff", "#1f2328")
+                .with_grays(["#c7c8c9", "#8f9194", "#575a5e", "#1f2328"])
+                .with_shade("#797b7e")
+                .with_box_fill("#e9e9ea"),
+            dark: Palette::new("#e6edf3", "#e6edf3", "#0d1117", "#e6edf3")
+                .with_grays(["#43484e", "#7a7f85", "#b0b6bc", "#e6edf3"])
+                .with_shade("#8f959b")
+                .with_box_fill("#23272d"),
+        }
+    }
+
+    /// Pure black and white in both modes
+    pub fn high_contrast() -> Self {
+        Self {
+            light: Palette::new("#000", "#000", "#fff", "#000")
+                .with_grays(["#bfbfbf", "#808080", "#404040", "#000"])
+                .with_shade("#666")
+                .with_box_fill("#e6e6e6"),
+            dark: Palette::new("#fff", "#fff", "#000", "#fff")
+                .with_grays(["#404040", "#808080", "#bfbfbf", "#fff"])
+                .with_shade("#999")
+                .with_box_fill("#1a1a1a"),
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::classic()
+    }
+}
+
+/// How colors are written into SVG attributes: as references to the
+/// `--aasvg-*` CSS variables, or as literal values from a palette, escaped
+/// so they cannot end the attribute.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Colors<'a> {
+    Variables,
+    Literal(&'a Palette),
+}
+
+impl<'a> Colors<'a> {
+    pub(crate) fn stroke(self) -> Escaped<'a> {
+        match self {
+            Colors::Variables => Escaped("var(--aasvg-stroke)"),
+            Colors::Literal(palette) => Escaped(&palette.stroke),
+        }
+    }
+
+    pub(crate) fn fill(self) -> Escaped<'a> {
+        match self {
+            Colors::Variables => Escaped("var(--aasvg-fill)"),
+            Colors::Literal(palette) => Escaped(&palette.fill),
+        }
+    }
+
+    pub(crate) fn bg(self) -> Escaped<'a> {
+        match self {
+            Colors::Variables => Escaped("var(--aasvg-bg)"),
+            Colors::Literal(palette) => Escaped(&palette.bg),
+        }
+    }
+
+    pub(crate) fn text(self) -> Escaped<'a> {
+        match self {
+            Colors::Variables => Escaped("var(--aasvg-text)"),
+            Colors::Literal(palette) => Escaped(&palette.text),
+        }
+    }
+
+    /// Fill for gray `level`, from 1 (`▁`) to 4 (`█`)
+    pub(crate) fn gray(self, level: u8) -> Escaped<'a> {
+        const VARIABLES: [&str; 4] = [
+            "var(--aasvg-gray-1)",
+            "var(--aasvg-gray-2)",
+            "var(--aasvg-gray-3)",
+            "var(--aasvg-gray-4)",
+        ];
+        let index = usize::from(level.clamp(1, 4) - 1);
+        match self {
+            Colors::Variables => Escaped(VARIABLES[index]),
+            Colors::Literal(palette) => Escaped(&palette.grays[index]),
+        }
+    }
+
+    pub(crate) fn shade(self) -> Escaped<'a> {
+        match self {
+            Colors::Variables => Escaped("var(--aasvg-shade)"),
+            Colors::Literal(palette) => Escaped(&palette.shade),
+        }
+    }
+
+    pub(crate) fn box_fill(self) -> Escaped<'a> {
+        match self {
+            Colors::Variables => Escaped("var(--aasvg-box-fill)"),
+            Colors::Literal(palette) => Escaped(&palette.box_fill),
+        }
+    }
+
+    /// Stroke width of thick lines, or None where it is written as a number
+    /// of pixels from the cell metrics
+    pub(crate) fn thick_width(self) -> Option<Escaped<'a>> {
+        match self {
+            Colors::Variables => Some(Escaped("var(--aasvg-thick-width)")),
+            Colors::Literal(_) => None,
+        }
+    }
+}
+
+/// Whether a palette value can be written into a CSS declaration without
+/// ending the declaration, the rule or the surrounding `<style>` element.
+pub(crate) fn is_css_safe(value: &str) -> bool {
+    !value.contains(['<', '>', '&', ';', '{', '}']) && !value.contains("/*")
+}
+
+/// Parse the CSS colors used in palettes into RGB components in `0..=1`.
+///
+/// Handles `#rgb`, `#rrggbb`, `rgb()`, `black`, `white` and the
+/// `color-mix(in srgb, …)` that [`Palette::new`] falls back to. Other
+/// values (including `none`) are not painted.
+pub(crate) fn parse_color(value: &str) -> Option<[f32; 3]> {
+    let value = value.trim();
+    if let Some(hex) = value.strip_prefix('#') {
+        let digits: Vec<u8> = hex
+            .chars()
+            .map(|c| c.to_digit(16).map(|d| d as u8))
+            .collect::<Option<_>>()?;
+        let rgb = match digits.len() {
+            3 => [digits[0] * 17, digits[1] * 17, digits[2] * 17],
+            6 => [
+                digits[0] * 16 + digits[1],
+                digits[2] * 16 + digits[3],
+                digits[4] * 16 + digits[5],
+            ],
+            _ => return None,
+        };
+        return Some(rgb.map(|v| f32::from(v) / 255.0));
+    }
+    if let Some(args) = value
+        .strip_prefix("rgb(")
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        let parts: Vec<f32> = args
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|part| !part.is_empty())
+            .map(|part| part.parse().ok())
+            .collect::<Option<_>>()?;
+        return match parts[..] {
+            [r, g, b] => Some([r / 255.0, g / 255.0, b / 255.0]),
+            _ => None,
+        };
+    }
+    if let Some(args) = value
+        .strip_prefix("color-mix(in srgb,")
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        // `<a> <p>%, <b>`: split at the last comma, as `a` may contain commas
+        let (first, second) = args.rsplit_once(',')?;
+        let (first, percent) = first.trim().rsplit_once(' ')?;
+        let p = percent.strip_suffix('%')?.parse::<f32>().ok()? / 100.0;
+        let a = parse_color(first)?;
+        let b = parse_color(second)?;
+        return Some([0, 1, 2].map(|i| a[i] * p + b[i] * (1.0 - p)));
+    }
+    match value {
+        "black" => Some([0.0, 0.0, 0.0]),
+        "white" => Some([1.0, 1.0, 1.0]),
+        _ => None,
+    }
+}
+
+/// Format RGB components in `0..=1` as `#rrggbb`
+fn to_hex(rgb: [f32; 3]) -> String {
+    let [r, g, b] = rgb.map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8);
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_is_classic() {
+        assert_eq!(Theme::default(), Theme::classic());
+        assert_eq!(Theme::default().dark.bg, "#1a1a1a");
+    }
+
+    #[test]
+    fn test_colors() {
+        let theme = Theme::github();
+        assert_eq!(Colors::Variables.stroke().0, "var(--aasvg-stroke)");
+        assert_eq!(Colors::Literal(&theme.light).stroke().0, "#1f2328");
+        assert_eq!(Colors::Literal(&theme.dark).bg().0, "#0d1117");
+
+        let mut palette = theme.light;
+        palette.fill = r#"red" onload="alert(1)"#.to_string();
+        assert_eq!(
+            Colors::Literal(&palette).fill().to_string(),
+            "red&quot; onload=&quot;alert(1)"
+        );
+    }
+
+    #[test]
+    fn test_grays() {
+        let palette = Palette::new("#000", "#111", "#fff", "#000");
+        assert_eq!(palette.grays[1], "#888888");
+        assert_eq!(palette.grays[3], "#111");
+        assert_eq!(palette.shade, "#707070");
+        assert_eq!(palette.box_fill, "#e7e7e7");
+        assert_eq!(
+            Palette::new("#000", "#000", "#fff", "#000").grays,
+            Theme::classic().light.grays
+        );
+
+        // Colors only the browser can mix are left to `color-mix()`
+        let palette = Palette::new("#000", "currentColor", "#fff", "#000");
+        assert_eq!(
+            palette.grays[1],
+            "color-mix(in srgb, currentColor 50%, #fff)"
+        );
+
+        let theme = Theme::classic();
+        assert_eq!(Colors::Variables.gray(1).0, "var(--aasvg-gray-1)");
+        assert_eq!(Colors::Literal(&theme.light).gray(4).0, "#000");
+        assert_eq!(Colors::Literal(&theme.dark).gray(4).0, "#fff");
+        assert_eq!(Colors::Literal(&theme.light).shade().0, "#666");
+    }
+
+    #[test]
+    fn test_css_safe() {
+        assert!(is_css_safe("#0969da"));
+        assert!(is_css_safe("rgb(0 0 0 / 50%)"));
+        assert!(is_css_safe("color-mix(in srgb, #111 50%, #fff)"));
+        assert!(!is_css_safe("red; } svg { display: none"));
+        assert!(!is_css_safe("red</style><script>"));
+        assert!(!is_css_safe("red /* comment"));
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#fff"), Some([1.0, 1.0, 1.0]));
+        assert_eq!(parse_color("#ff0000"), Some([1.0, 0.0, 0.0]));
+        assert_eq!(parse_color("rgb(0, 255, 0)"), Some([0.0, 1.0, 0.0]));
+        assert_eq!(
+            parse_color("color-mix(in srgb, #000 25%, #fff)"),
+            Some([0.75, 0.75, 0.75])
+        );
+        assert_eq!(parse_color("none"), None);
+        assert_eq!(parse_color("#12"), None);
+    }
+
+    #[test]
+    fn test_presets_differ() {
+        assert_ne!(Theme::github(), Theme::classic());
+        assert_ne!(Theme::high_contrast(), Theme::classic());
+    }
+}
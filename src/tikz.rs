@@ -0,0 +1,277 @@
+//! TikZ output for LaTeX documents.
+//!
+//! The diagram's [`Shape`]s become `\draw`/`\fill` commands and text runs
+//! become nodes, so text is set in the document's own font. Coordinates stay
+//! in SVG pixels; the picture's `x`/`y` units scale them to 0.75pt and flip
+//! the y axis.
+
+use std::fmt::Write;
+
+use crate::diagram::Diagram;
+use crate::path::{format_coord, Vec2};
+use crate::shape::{quad_to_cubic, Paint, Segment, Shape};
+use crate::svg::{RenderOptions, Viewport};
+use crate::theme::{parse_color, Palette};
+
+/// Generate a `tikzpicture` environment for a parsed diagram.
+///
+/// Colors are declared with `\providecolor`, so a document can define
+/// `aasvg-stroke`, `aasvg-text` and friends beforehand to restyle every
+/// diagram. Otherwise they come from [`RenderOptions::fixed_palette`].
+pub(crate) fn generate_tikz(diagram: &Diagram, options: &RenderOptions) -> String {
+    let palette = options.fixed_palette();
+    let mut tikz = String::new();
+
+    tikz.push_str("\\begin{tikzpicture}[x=0.75pt, y=-0.75pt, line width=0.75pt, line cap=round, line join=round]\n");
+    for (name, value) in palette_colors(palette) {
+        let [r, g, b] = parse_color(value).unwrap_or([0.0, 0.0, 0.0]);
+        let _ = writeln!(
+            tikz,
+            "\\providecolor{{{}}}{{rgb}}{{{},{},{}}}",
+            name,
+            format_coord(r.into()),
+            format_coord(g.into()),
+            format_coord(b.into())
+        );
+    }
+
+    let viewport = Viewport::new(diagram, options);
+    let corners = (
+        point(Vec2::new(viewport.x, viewport.y)),
+        point(Vec2::new(
+            viewport.x + viewport.width,
+            viewport.y + viewport.height,
+        )),
+    );
+    if options.width.is_some() || options.height.is_some() || options.margin != 0.0 {
+        // The picture would otherwise grow to fit its content
+        let _ = writeln!(
+            tikz,
+            "\\useasboundingbox ({}) rectangle ({});",
+            corners.0, corners.1
+        );
+    }
+    if options.backdrop {
+        let _ = writeln!(
+            tikz,
+            "\\fill[aasvg-bg] ({}) rectangle ({});",
+            corners.0, corners.1
+        );
+    }
+
+    for shape in diagram.shapes(options) {
+        write_shape(&mut tikz, &shape);
+    }
+
+    if !options.disable_text {
+        let metrics = options.metrics();
+        for run in &diagram.text {
+            let _ = writeln!(
+                tikz,
+                "\\node[text=aasvg-text, anchor=base, inner sep=0] at ({}) {{{}}};",
+                point(run.anchor(&metrics)),
+                escape_latex(&run.text)
+            );
+        }
+    }
+
+    tikz.push_str("\\end{tikzpicture}\n");
+    tikz
+}
+
+/// Write one `\draw`, `\fill` or `\filldraw` command
+fn write_shape(tikz: &mut String, shape: &Shape) {
+    let mut style = Vec::new();
+    let command = match (shape.fill, shape.stroke) {
+        (Some(fill), Some(stroke)) => {
+            style.push(format!("fill={}", color_name(fill)));
+            style.push(format!("draw={}", color_name(stroke)));
+            "filldraw"
+        }
+        (Some(fill), None) => {
+            style.push(color_name(fill).to_string());
+            "fill"
+        }
+        (None, Some(stroke)) => {
+            style.push(color_name(stroke).to_string());
+            "draw"
+        }
+        (None, None) => return,
+    };
+    if shape.stroke.is_some() && shape.width != 1.0 {
+        style.push(format!("line width={}pt", format_coord(shape.width * 0.75)));
+    }
+    if let (Some((dash, gap)), Some(_)) = (shape.dash, shape.stroke) {
+        style.push(format!(
+            "dash pattern=on {}pt off {}pt",
+            format_coord(dash * 0.75),
+            format_coord(gap * 0.75)
+        ));
+    }
+
+    let _ = write!(tikz, "\\{}[{}]", command, style.join(", "));
+    let mut current = Vec2::new(0.0, 0.0);
+    for segment in &shape.segments {
+        match *segment {
+            Segment::Move(p) => {
+                let _ = write!(tikz, " ({})", point(p));
+                current = p;
+            }
+            Segment::Line(p) => {
+                let _ = write!(tikz, " -- ({})", point(p));
+                current = p;
+            }
+            // A single TikZ control point is not quadratic, so elevate it
+            Segment::Quad(c, p) => {
+                let (c1, c2) = quad_to_cubic(current, c, p);
+                let _ = write!(
+                    tikz,
+                    " .. controls ({}) and ({}) .. ({})",
+                    point(c1),
+                    point(c2),
+                    point(p)
+                );
+                current = p;
+            }
+            Segment::Cubic(c1, c2, p) => {
+                let _ = write!(
+                    tikz,
+                    " .. controls ({}) and ({}) .. ({})",
+                    point(c1),
+                    point(c2),
+                    point(p)
+                );
+                current = p;
+            }
+            Segment::Close => tikz.push_str(" -- cycle"),
+        }
+    }
+    tikz.push_str(";\n");
+}
+
+/// Color names and their values from `palette`
+fn palette_colors(palette: &Palette) -> [(&'static str, &str); 10] {
+    [
+        ("aasvg-stroke", &palette.stroke),
+        ("aasvg-fill", &palette.fill),
+        ("aasvg-bg", &palette.bg),
+        ("aasvg-text", &palette.text),
+        ("aasvg-gray-1", &palette.grays[0]),
+        ("aasvg-gray-2", &palette.grays[1]),
+        ("aasvg-gray-3", &palette.grays[2]),
+        ("aasvg-gray-4", &palette.grays[3]),
+        ("aasvg-shade", &palette.shade),
+        ("aasvg-box-fill", &palette.box_fill),
+    ]
+}
+
+fn color_name(paint: Paint) -> &'static str {
+    match paint {
+        Paint::Stroke => "aasvg-stroke",
+        Paint::Fill => "aasvg-fill",
+        Paint::Bg => "aasvg-bg",
+        Paint::Gray(1) => "aasvg-gray-1",
+        Paint::Gray(2) => "aasvg-gray-2",
+        Paint::Gray(3) => "aasvg-gray-3",
+        Paint::Gray(_) => "aasvg-gray-4",
+        Paint::Shade => "aasvg-shade",
+        Paint::BoxFill => "aasvg-box-fill",
+    }
+}
+
+fn point(p: Vec2) -> String {
+    format!("{},{}", format_coord(p.x), format_coord(p.y))
+}
+
+/// Escape characters that are special in LaTeX text
+fn escape_latex(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => result.push_str("\\textbackslash{}"),
+            '{' | '}' | '$' | '&' | '#' | '_' | '%' => {
+                result.push('\\');
+                result.push(c);
+            }
+            '^' => result.push_str("\\textasciicircum{}"),
+            '~' => result.push_str("\\textasciitilde{}"),
+            '<' => result.push_str("\\textless{}"),
+            '>' => result.push_str("\\textgreater{}"),
+            '|' => result.push_str("\\textbar{}"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::svg::ColorMode;
+    use crate::theme::Theme;
+
+    fn tikz(input: &str, options: &RenderOptions) -> String {
+        generate_tikz(&Diagram::parse(input, options), options)
+    }
+
+    /// The `\providecolor` default for `name`
+    fn provided(tikz: &str, name: &str) -> Option<String> {
+        let prefix = format!("\\providecolor{{{}}}{{rgb}}{{", name);
+        let start = tikz.find(&prefix)? + prefix.len();
+        let end = start + tikz[start..].find('}')?;
+        Some(tikz[start..end].to_string())
+    }
+
+    #[test]
+    fn test_tikz_environment() {
+        let out = tikz("+--+\n|Hi|\n+--+", &RenderOptions::new());
+        assert!(out.starts_with("\\begin{tikzpicture}[x=0.75pt, y=-0.75pt"));
+        assert!(out.ends_with("\\end{tikzpicture}\n"));
+        assert!(out.contains("\\draw[aasvg-stroke] (8,16) -- (8,48);"));
+        assert!(out.contains(" at (20,36) {Hi};"));
+        assert!(!out.contains("\\useasboundingbox"));
+
+        let sized = RenderOptions::new()
+            .with_size(Some(10), None)
+            .with_margin(-4.0)
+            .with_backdrop(true);
+        let out = tikz("+--+\n|Hi|\n+--+", &sized);
+        assert!(out.contains("\\useasboundingbox (4,4) rectangle (84,60);"));
+        assert!(out.contains("\\fill[aasvg-bg] (4,4) rectangle (84,60);"));
+    }
+
+    #[test]
+    fn test_tikz_decorations() {
+        let out = tikz("-->  o", &RenderOptions::new());
+        assert!(out.contains("\\fill[aasvg-fill] (32,16) -- "));
+        assert!(out.contains("-- cycle;"));
+        assert!(out.contains("\\filldraw[fill=aasvg-bg, draw=aasvg-stroke] (54,16) .. controls"));
+    }
+
+    #[test]
+    fn test_tikz_line_styles() {
+        let out = tikz("━━━\n\n┄┄┄", &RenderOptions::new());
+        assert!(out.contains("\\draw[aasvg-stroke, line width=2.25pt]"));
+        assert!(out.contains("\\draw[aasvg-stroke, dash pattern=on 3pt off 1.5pt]"));
+    }
+
+    #[test]
+    fn test_tikz_palette() {
+        let options = RenderOptions::new().with_theme(Theme::classic());
+        let light = tikz("-", &options);
+        assert_eq!(provided(&light, "aasvg-stroke").unwrap(), "0,0,0");
+        let dark = tikz("-", &options.with_color_mode(ColorMode::Dark));
+        assert_eq!(provided(&dark, "aasvg-stroke").unwrap(), "1,1,1");
+        assert_eq!(
+            provided(&dark, "aasvg-bg").unwrap(),
+            "0.10196,0.10196,0.10196"
+        );
+    }
+
+    #[test]
+    fn test_escape_latex() {
+        assert_eq!(escape_latex("50% & $x_1$"), "50\\% \\& \\$x\\_1\\$");
+        assert_eq!(escape_latex("a\\b"), "a\\textbackslash{}b");
+        assert_eq!(escape_latex("{~}"), "\\{\\textasciitilde{}\\}");
+    }
+}
@@ -2,7 +2,10 @@
 //!
 //! These tests verify that complete diagrams render correctly.
 
-use aasvg::{render, render_with_options, RenderOptions};
+use aasvg::{
+    parse, render, render_with_options, ColorMode, CssScope, Description, Direction, RenderOptions,
+    Scheme, SourceOverlay,
+};
 
 const FIXTURES_DIR: &str = "tests/fixtures";
 
@@ -97,6 +100,15 @@ fn test_fill_uses_variable() {
     assert!(svg.contains(r#"fill="var(--aasvg-fill)"#));
 }
 
+#[test]
+fn test_gray_uses_variable() {
+    let svg = render("▁▂▃█ ◍");
+    assert!(svg.contains(r#"fill="var(--aasvg-gray-1)"#));
+    assert!(svg.contains(r#"fill="var(--aasvg-gray-4)"#));
+    assert!(svg.contains(r#"fill="var(--aasvg-shade)"#));
+    assert!(!svg.contains("rgb("));
+}
+
 #[test]
 fn test_backdrop_uses_variable() {
     let options = RenderOptions::new().with_backdrop(true);
@@ -117,6 +129,171 @@ fn test_backdrop_option() {
     // The without_backdrop might still have rect for other reasons, so just verify the with case
 }
 
+#[test]
+fn test_scoped_css_option() {
+    let options = RenderOptions::new().with_css_scope(CssScope::Unique);
+    let first = render_with_options("+--+\n|A |\n+--+", &options);
+    let second = render_with_options("+--+\n|B |\n+--+", &options);
+
+    // Each SVG sets its variables on itself only
+    assert!(!first.contains(":root"));
+    assert!(!second.contains(":root"));
+    let id = |svg: &str| svg.split('"').nth(5).unwrap().to_string();
+    assert_ne!(id(&first), id(&second));
+    assert!(first.contains(&format!("svg#{} {{", id(&first))));
+}
+
+#[test]
+fn test_join_paths_option() {
+    let input = "+--+\n|  |\n+--+";
+    let separate = render(input);
+    let joined = render_with_options(input, &RenderOptions::new().with_join_paths(true));
+
+    assert_eq!(separate.matches("<path").count(), 4);
+    assert_eq!(joined.matches("<path").count(), 1);
+    assert!(joined.contains(r#"<path d="M 8,16 L 8,48 L 32,48 L 32,16 Z""#));
+    assert!(joined.contains(r#"stroke-linejoin="round""#));
+}
+
+#[test]
+fn test_fill_boxes_option() {
+    let input = ".--.   +--+\n|  |---|  |\n'--'   +--+";
+    assert!(!render(input).contains("--aasvg-box-fill"));
+
+    let svg = render_with_options(input, &RenderOptions::new().with_fill_boxes(true));
+    assert!(svg.contains("--aasvg-box-fill: #e6e6e6;"));
+    assert!(svg.contains("--aasvg-box-fill: #313131;"));
+    let group = svg.find(r#"<g fill="var(--aasvg-box-fill)">"#).unwrap();
+    assert!(group < svg.find("<path").unwrap());
+    assert_eq!(svg.matches("<rect").count(), 2);
+    assert!(svg.contains(r#"<rect x="64" y="16" width="24" height="32"/>"#));
+
+    let inline = RenderOptions::new()
+        .with_fill_boxes(true)
+        .with_color_mode(ColorMode::Inline(Scheme::Light));
+    let svg = render_with_options(input, &inline);
+    assert!(svg.contains(r##"<g fill="#e6e6e6">"##));
+}
+
+#[test]
+fn test_accessibility_options() {
+    let input = "+-------+\n| cache |<-- \"hits\"\n+-------+";
+    let options = RenderOptions::new()
+        .with_css_scope(CssScope::Unique)
+        .with_title("Cache <fast>")
+        .with_description(Description::Auto);
+    let svg = render_with_options(input, &options);
+
+    let id = svg.split('"').nth(5).unwrap();
+    assert!(svg.contains(&format!(
+        r#" role="img" aria-labelledby="{id}-title {id}-desc">"#
+    )));
+    assert!(svg.contains(&format!(
+        r#"<title id="{id}-title">Cache &lt;fast&gt;</title>"#
+    )));
+    assert!(svg.contains(&format!(
+        r#"<desc id="{id}-desc">cache, &quot;hits&quot;</desc>"#
+    )));
+    // The title comes first, as screen readers expect
+    assert!(svg.find("<title").unwrap() < svg.find("<style").unwrap());
+}
+
+#[test]
+fn test_debug_grid_options() {
+    let input = "+--+\n|  |\n+--+";
+    let svg = render_with_options(input, &RenderOptions::new().with_show_grid(true));
+    assert!(svg.contains(r#"<g class="aasvg-grid""#));
+    assert!(!svg.contains("aasvg-used"));
+    assert_eq!(svg.matches("<circle").count(), 8);
+
+    let svg = render_with_options(input, &RenderOptions::new().with_show_used(true));
+    assert!(!svg.contains("aasvg-grid"));
+    assert_eq!(svg.matches("<rect").count(), 4);
+}
+
+#[test]
+fn test_source_overlay_option() {
+    let options = RenderOptions::new()
+        .with_disable_text(true)
+        .with_source_overlay(SourceOverlay::Over)
+        .with_color_mode(ColorMode::Inline(Scheme::Light));
+    let svg = render_with_options("+--+\n|Hi|\n+--+", &options);
+    assert!(svg.contains(r##"<g class="aasvg-source" fill="#000" fill-opacity="0.4">"##));
+    assert_eq!(svg.matches("<text").count(), 12);
+    assert!(svg.contains(r#"<text x="24" y="36">i</text>"#));
+}
+
+#[test]
+fn test_viewport_options() {
+    let input = "+--+\n|  |\n+--+";
+    let svg = render_with_options(input, &RenderOptions::new().with_fill_container(true));
+    assert!(!svg.contains(" width=\"40\""));
+    assert!(svg.contains(r#"viewBox="0 0 40 64""#));
+
+    // Every diagram in a gallery gets the same 20x5 cell viewport
+    let gallery = RenderOptions::new().with_size(Some(20), Some(5));
+    for input in [input, "-->", "+------+\n|      |\n|      |\n+------+"] {
+        let svg = render_with_options(input, &gallery);
+        assert!(svg.contains(r#" width="168" height="96" viewBox="0 0 168 96""#));
+    }
+
+    let svg = render_with_options(input, &RenderOptions::new().with_margin(8.0));
+    assert!(svg.contains(r#" width="56" height="80" viewBox="-8 -8 56 80""#));
+}
+
+#[test]
+fn test_root_attribute_options() {
+    let options = RenderOptions::new()
+        .with_attribute("class", "diagram figure")
+        .with_attribute("font-family", "Iosevka, monospace");
+    let svg = render_with_options("+--+\n|  |\n+--+", &options);
+    assert!(svg.contains(r#" class="diagram figure" "#));
+    assert!(svg.contains(r#" font-family="Iosevka, monospace" "#));
+    assert_eq!(svg.matches(" class=").count(), 1);
+}
+
+#[test]
+fn test_cell_metrics_options() {
+    let input = "+--+\n|Hi|-->\n+--+";
+    let default = RenderOptions::new();
+    let explicit = RenderOptions::new()
+        .with_cell_width(8.0)
+        .with_aspect(2.0)
+        .with_font_size(13.0);
+    assert_eq!(
+        render_with_options(input, &explicit),
+        render_with_options(input, &default)
+    );
+
+    // 10x25 cells: the text sits in the middle of the box in every format
+    let options = RenderOptions::new()
+        .with_cell_width(10.0)
+        .with_aspect(2.5)
+        .with_font_size(20.0);
+    let svg = render_with_options(input, &options);
+    assert!(svg.contains(r#"<path d="M 10,75 L 40,75""#));
+    assert!(svg.contains(r#"<text x="25" y="56.15385">Hi</text>"#));
+    let tikz = aasvg::render_tikz(input, &options);
+    assert!(tikz.contains(" (10,75) -- (40,75);"));
+    assert!(tikz.contains(" at (25,56.15385) {Hi};"));
+}
+
+#[test]
+fn test_cell_metrics_strokes() {
+    let input = "  |\n--)--\n  |\n\n┄┄┄┄";
+    let svg = render(input);
+    assert!(svg.contains(r#" stroke-dasharray="4,2"/>"#));
+    assert!(svg.contains(r#" stroke-width="3"/>"#));
+
+    // Dashes and the gap under a jump scale with the cell width
+    let options = RenderOptions::new().with_cell_width(16.0);
+    let svg = render_with_options(input, &options);
+    assert!(svg.contains(r#" stroke-dasharray="8,4"/>"#));
+    assert!(svg.contains(r#" stroke="var(--aasvg-bg)" stroke-width="6"/>"#));
+    let tikz = aasvg::render_tikz(input, &options);
+    assert!(tikz.contains("dash pattern=on 6pt off 3pt"));
+}
+
 #[test]
 fn test_disable_text_option() {
     let with_text = render("Hello");
@@ -197,6 +374,48 @@ fn test_fixture_example() {
     assert!(svg.contains("prefers-color-scheme: dark"));
 }
 
+#[test]
+fn test_fixture_box_drawing() {
+    let input = load_fixture("box_drawing.txt");
+    let diagram = aasvg::parse(&input);
+
+    // Box-drawing characters become paths, only the labels remain as text
+    let text: Vec<_> = diagram.text.iter().map(|run| run.text.as_str()).collect();
+    assert_eq!(
+        text,
+        vec!["Square", "Round", "Double", "Heavy", "Dashed", "Mixed"]
+    );
+
+    // Rounded corners are drawn as curves
+    assert_eq!(diagram.paths.iter().filter(|p| p.is_curved()).count(), 4);
+    assert!(diagram.paths.iter().any(|p| p.style.double));
+    assert!(diagram.paths.iter().any(|p| p.style.dashed));
+
+    let svg = render(&input);
+    assert!(svg.contains(" C "));
+    assert!(!svg.contains('┌'));
+}
+
+#[test]
+fn test_double_box_corners() {
+    let svg = render("╔══╗\n║  ║\n╚══╝");
+
+    // Outer rails meet at the outer corners and inner rails at the inner
+    // ones, without crossing
+    for d in [
+        "M 6,14 L 6,50",
+        "M 10,18 L 10,46",
+        "M 34,14 L 34,50",
+        "M 30,18 L 30,46",
+        "M 6,14 L 34,14",
+        "M 10,18 L 30,18",
+        "M 10,46 L 30,46",
+        "M 6,50 L 34,50",
+    ] {
+        assert!(svg.contains(&format!("<path d=\"{}\"", d)), "missing {}", d);
+    }
+}
+
 // ============================================================================
 // Edge cases
 // ============================================================================
@@ -272,6 +491,42 @@ fn test_double_line() {
     assert!(svg.contains("<path"));
 }
 
+#[test]
+fn test_thick_line() {
+    let svg = render("━━━━━\n\n┃\n┃");
+    assert_eq!(
+        count_pattern(&svg, "stroke-width=\"var(--aasvg-thick-width)\""),
+        2
+    );
+    assert!(svg.contains("--aasvg-thick-width: 3px;"));
+
+    // Only diagrams with thick lines declare the width
+    assert!(!render("─────").contains("--aasvg-thick-width"));
+
+    // The width follows the cell width, also with inline colors
+    let options = RenderOptions::new().with_cell_width(16.0);
+    let svg = render_with_options("━━━━━", &options);
+    assert!(svg.contains("--aasvg-thick-width: 6px;"));
+    let svg = render_with_options(
+        "━━━━━",
+        &options.with_color_mode(ColorMode::Inline(Scheme::Light)),
+    );
+    assert!(svg.contains(" stroke-width=\"6\""));
+}
+
+#[test]
+fn test_heavy_dashed_line() {
+    let svg = render("┅┅┅┅\n\n┄┄┄┄\n\n┇\n┇\n┇");
+    assert_eq!(count_pattern(&svg, "stroke-dasharray=\"4,2\""), 3);
+    assert_eq!(
+        count_pattern(
+            &svg,
+            "stroke-dasharray=\"4,2\" stroke-width=\"var(--aasvg-thick-width)\""
+        ),
+        2
+    );
+}
+
 #[test]
 fn test_squiggle_line() {
     let svg = render("~~~~~");
@@ -280,6 +535,56 @@ fn test_squiggle_line() {
     assert!(svg.contains(" Q "));
 }
 
+// ============================================================================
+// Graph export tests
+// ============================================================================
+
+#[test]
+fn test_graph_export() {
+    let diagram = r#"
++--------+     +---------+     +----------+
+| client |---->|  proxy  |<--->| "server" |
++--------+     +---------+     +----------+
+                    |
+                    |
+               .---------.
+              ( metrics   )
+               '---------'
+"#;
+    let graph = parse(diagram).to_graph();
+    let labels: Vec<_> = graph.nodes.iter().map(|n| n.label.as_str()).collect();
+    assert_eq!(labels, vec!["client", "proxy", "\"server\"", "metrics"]);
+    assert!(graph.nodes[3].region.is_rounded());
+
+    let edges: Vec<_> = graph
+        .edges
+        .iter()
+        .map(|e| (e.from, e.to, e.direction))
+        .collect();
+    assert_eq!(
+        edges,
+        vec![
+            (0, 1, Direction::Forward),
+            (1, 2, Direction::Both),
+            (1, 3, Direction::None),
+        ]
+    );
+
+    let json = graph.to_json();
+    assert!(json.contains(r#""label": "\"server\"""#));
+    assert!(json.contains(r#"{"from": 0, "to": 1, "direction": "forward"}"#));
+
+    let dot = graph.to_dot();
+    assert!(dot.starts_with("digraph {"));
+    assert!(dot.contains(r#"n2 [label="\"server\""];"#));
+    assert!(dot.contains("n1 -> n2 [dir=both];"));
+
+    let mermaid = graph.to_mermaid();
+    assert!(mermaid.starts_with("flowchart TD\n"));
+    assert!(mermaid.contains(r#"n3("metrics")"#));
+    assert!(mermaid.contains("n1 --- n3"));
+}
+
 // ============================================================================
 // Reference comparison test
 // ============================================================================